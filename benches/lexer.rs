@@ -0,0 +1,50 @@
+//lexing time for generated programs of doubling size
+//  run with `cargo bench --bench lexer`, the time per byte should stay about
+//  the same from one row to the next when lexing is linear
+use cpsc_323_compiler_project::lexer;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+//statement repeated to build the inputs, covers every kind of token
+const STATEMENT: &str = "total = total + 12 * (count - 3.5); ! running sum !\n";
+
+//each size is lexed this many times and the fastest run is kept
+const RUNS: usize = 5;
+
+fn main() {
+    println!("{:>12} {:>12} {:>12}", "bytes", "time", "ns/byte");
+
+    let mut statements = 1_000;
+    while statements <= 64_000 {
+        let source = program(statements);
+        let time = fastest(&source);
+        println!(
+            "{:>12} {:>12.2?} {:>12.2}",
+            source.len(),
+            time,
+            time.as_nanos() as f64 / source.len() as f64
+        );
+        statements *= 2;
+    }
+}
+
+fn program(statements: usize) -> String {
+    let mut source = String::from("begin\nint count, total;\n");
+    for _ in 0..statements {
+        source.push_str(STATEMENT);
+    }
+    source.push_str("output(total)\nend\n");
+    source
+}
+
+fn fastest(source: &str) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let tokens = lexer::lexer(black_box(source)).expect("generated program lexes");
+            black_box(tokens);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
@@ -0,0 +1,20 @@
+//refuse to build when the grammar in src/grammar.rs is not LL(1)
+//  the parser reads its table from that grammar, a conflict would mean it
+//  silently picks one of the competing productions
+#[allow(dead_code)]
+#[path = "src/grammar.rs"]
+mod grammar;
+
+use std::process;
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/grammar.rs");
+
+    if let Err(conflicts) = grammar::rat().table() {
+        for conflict in conflicts.iter() {
+            eprintln!("error: {}", conflict);
+        }
+        eprintln!("error: the grammar in src/grammar.rs is not LL(1)");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,904 @@
+//typed syntax tree for Rat programs
+//  built from the parser's concrete parse tree once a parse succeeds, so later
+//  passes work on statements and expressions instead of grammar symbols
+use crate::error::CompileError;
+use crate::error::{Location, Note};
+use crate::parser::{ParseTree, Symbols, Symbols::*};
+use crate::tokens::{Span, TokenKind, TokenType};
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+//declarable types
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Type {
+    Int,
+    Bool,
+    Float,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+            Type::Float => write!(f, "float"),
+        }
+    }
+}
+
+impl Type {
+    //int and float can be used in arithmetic
+    pub fn is_numeric(self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    //type of arithmetic on two numbers, an int meeting a float is promoted to float
+    pub fn promote(self, other: Type) -> Type {
+        if self == Type::Float || other == Type::Float {
+            Type::Float
+        } else {
+            self
+        }
+    }
+
+    //whether a variable of this type can hold a value of the other type
+    //  an int widens to float, nothing narrows
+    pub fn accepts(self, value: Type) -> bool {
+        self == value || (self == Type::Float && value == Type::Int)
+    }
+}
+
+//a variable's name where it appears in the program
+//  the name is the lexer's interned copy, shared by every use of it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ident {
+    pub name: Rc<str>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+// begin ... end
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Statement>,
+    pub span: Span,
+}
+
+// int a, b, c;
+#[derive(Clone, Debug, PartialEq)]
+pub struct Declaration {
+    pub ty: Type,
+    pub names: Vec<Ident>,
+    pub span: Span,
+}
+
+// a = expr
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assign {
+    pub target: Ident,
+    pub value: Expr,
+    pub span: Span,
+}
+
+// if cond then stmt else stmt endif
+#[derive(Clone, Debug, PartialEq)]
+pub struct If {
+    pub condition: Expr,
+    pub then_branch: Box<Statement>,
+    pub else_branch: Option<Box<Statement>>,
+    pub span: Span,
+}
+
+// while cond do stmt; ... whileend
+#[derive(Clone, Debug, PartialEq)]
+pub struct While {
+    pub condition: Expr,
+    pub body: Box<Statement>,
+    pub span: Span,
+}
+
+// input(a, b)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Input {
+    pub targets: Vec<Ident>,
+    pub span: Span,
+}
+
+// output(expr, expr)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Output {
+    pub values: Vec<Expr>,
+    pub span: Span,
+}
+
+// for i = start, end do stmt; ... forend
+//  counts up by one while the variable is at most end
+//  end is evaluated again before every pass, so a body that changes what it
+//  reads changes how often the loop runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct For {
+    pub variable: Ident,
+    pub start: Expr,
+    pub end: Expr,
+    pub body: Box<Statement>,
+    pub span: Span,
+}
+
+// do stmt; ... doend while cond
+//  the body runs once before the condition is tested
+#[derive(Clone, Debug, PartialEq)]
+pub struct DoWhile {
+    pub body: Box<Statement>,
+    pub condition: Expr,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    Declaration(Declaration),
+    Assign(Assign),
+    If(If),
+    While(While),
+    For(For),
+    DoWhile(DoWhile),
+    Block(Block),
+    Input(Input),
+    Output(Output),
+    Expr(Expr),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Declaration(d) => d.span,
+            Statement::Assign(a) => a.span,
+            Statement::If(i) => i.span,
+            Statement::While(w) => w.span,
+            Statement::For(f) => f.span,
+            Statement::DoWhile(d) => d.span,
+            Statement::Block(b) => b.span,
+            Statement::Input(i) => i.span,
+            Statement::Output(o) => o.span,
+            Statement::Expr(e) => e.span,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+// and, or
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Relop {
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryOp::Add => write!(f, "+"),
+            BinaryOp::Sub => write!(f, "-"),
+            BinaryOp::Mul => write!(f, "*"),
+            BinaryOp::Div => write!(f, "/"),
+        }
+    }
+}
+
+impl fmt::Display for LogicalOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogicalOp::And => write!(f, "and"),
+            LogicalOp::Or => write!(f, "or"),
+        }
+    }
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnaryOp::Not => write!(f, "not"),
+            UnaryOp::Neg => write!(f, "-"),
+        }
+    }
+}
+
+impl Relop {
+    //< <= >= > only make sense for numbers, == and <> work on any type
+    pub fn is_ordering(self) -> bool {
+        !matches!(self, Relop::Equal | Relop::NotEqual)
+    }
+}
+
+impl fmt::Display for Relop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Relop::Less => write!(f, "<"),
+            Relop::LessEqual => write!(f, "<="),
+            Relop::Equal => write!(f, "=="),
+            Relop::NotEqual => write!(f, "<>"),
+            Relop::GreaterEqual => write!(f, ">="),
+            Relop::Greater => write!(f, ">"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind {
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Relop {
+        op: Relop,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    //the right side is only evaluated when the left does not decide the result
+    Logical {
+        op: LogicalOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Literal(Literal),
+    Var(Ident),
+}
+
+impl Expr {
+    fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr {
+            span: lhs.span.to(rhs.span),
+            kind: ExprKind::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        }
+    }
+
+    fn relop(op: Relop, lhs: Expr, rhs: Expr) -> Expr {
+        Expr {
+            span: lhs.span.to(rhs.span),
+            kind: ExprKind::Relop {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        }
+    }
+
+    fn logical(op: LogicalOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr {
+            span: lhs.span.to(rhs.span),
+            kind: ExprKind::Logical {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        }
+    }
+}
+
+//deepest a program may nest statements and expressions, counted together
+//  every pass over the syntax tree recurses once per level, so a program past
+//  this is reported instead of running any of them out of stack, with room to
+//  spare on a 2MB thread in a debug build
+//  a chain like a + b + c nests one level per operator, since it folds to the left
+pub const MAX_NESTING: usize = 256;
+
+//turn a successful parse into a program
+//  fails on literals that do not fit, on assignments to something other than
+//  a variable and on nesting deeper than MAX_NESTING
+pub fn from_parse_tree(tree: &ParseTree) -> Result<Program, Vec<CompileError>> {
+    let mut lowering = Lowering {
+        tree,
+        errors: Vec::new(),
+        depth: 0,
+        too_deep: false,
+    };
+    let statements = lowering.statement(tree.root).into_iter().collect();
+
+    if lowering.errors.is_empty() {
+        Ok(Program { statements })
+    } else {
+        Err(lowering.errors)
+    }
+}
+
+//walks the parse tree, matching on the symbols of each node's children
+//  the shapes follow the productions in grammar.rs
+struct Lowering<'a> {
+    tree: &'a ParseTree,
+    errors: Vec<CompileError>,
+    depth: usize,   // levels of nesting around the node being lowered
+    too_deep: bool, // nesting past MAX_NESTING has been reported
+}
+
+impl<'a> Lowering<'a> {
+    fn shape(&self, id: usize) -> Vec<Symbols> {
+        self.tree.nodes[id]
+            .children
+            .iter()
+            .map(|child| self.tree.nodes[*child].symbol)
+            .collect()
+    }
+
+    fn child(&self, id: usize, index: usize) -> usize {
+        self.tree.nodes[id].children[index]
+    }
+
+    //lower something nested one level inside each of `levels`, the nodes that
+    //  open those levels
+    //  None once that goes past MAX_NESTING, reported at the first node that does
+    fn nested<T>(
+        &mut self,
+        levels: &[usize],
+        lower: impl FnOnce(&mut Self) -> Option<T>,
+    ) -> Option<T> {
+        let room = MAX_NESTING - self.depth;
+        if levels.len() > room {
+            if self.too_deep {
+                return None;
+            }
+            self.too_deep = true;
+            let at = levels[room];
+            let span = match self.tree.first_token(at) {
+                Some(token) => token.span,
+                None => self.tree.span(at),
+            };
+            self.errors.push(CompileError::Semantic {
+                location: Location::from(span),
+                message: "program is nested too deeply".to_string(),
+                notes: vec![Note::new(&format!(
+                    "statements and expressions can nest at most {} levels",
+                    MAX_NESTING
+                ))],
+            });
+            return None;
+        }
+
+        self.depth += levels.len();
+        let lowered = lower(self);
+        self.depth -= levels.len();
+        lowered
+    }
+
+    //the operator of every link in a left associative chain such as a + b - c,
+    //  which nests one Prime node per operator
+    //  callers lower a lone operand directly, without going through nested(), so
+    //  each parenthesised level costs as few stack frames as possible
+    fn chain(&self, id: usize, operators: &[Symbols]) -> Vec<usize> {
+        let mut links = Vec::new();
+        let mut id = id;
+        while self
+            .shape(id)
+            .first()
+            .is_some_and(|symbol| operators.contains(symbol))
+        {
+            links.push(self.child(id, 0));
+            id = self.child(id, 2);
+        }
+        links
+    }
+
+    //None for the empty statement and for statements that already produced an error
+    //  each kind is lowered in its own method, which keeps this frame small since
+    //  nested statements come back through here once per level
+    fn statement(&mut self, id: usize) -> Option<Statement> {
+        match self.shape(id).as_slice() {
+            [DECLAR] => Some(Statement::Declaration(self.declaration(self.child(id, 0)))),
+            [EXPR, ASSIGN] => self.expr_statement(id),
+            [IF, EXPR, THEN, STATEMENT, ELSE_PART, ENDIF] => self.conditional(id),
+            [PLAIN_STATEMENT] => self.statement(self.child(id, 0)),
+            [WHILE, EXPR, DO, STATEMENT, MORESTATEMENTS, WHILEEND] => self.while_loop(id),
+            [FOR, ID_NT, EQUAL, EXPR, COMMA, EXPR, DO, STATEMENT, MORESTATEMENTS, FOREND] => {
+                self.for_loop(id)
+            }
+            [DO, STATEMENT, MORESTATEMENTS, DOEND, WHILE, EXPR] => self.do_while(id),
+            [BEGIN, STATEMENT, MORESTATEMENTS, END] => self.block(id),
+            [INPUT, L_PAREN, ID_NT, MOREIDS, R_PAREN] => self.input(id),
+            [OUTPUT, L_PAREN, EXPR, MOREEXPRS, R_PAREN] => self.output(id),
+            _ => None,
+        }
+    }
+
+    // while Expression do Statement MoreStatements whileend
+    fn while_loop(&mut self, id: usize) -> Option<Statement> {
+        let span = self.tree.span(id);
+        let condition = self.expr(self.child(id, 1));
+        let body = self.statements(self.child(id, 3), self.child(id, 4));
+        Some(Statement::While(While {
+            condition: condition?,
+            body: Box::new(self.single(body, span)),
+            span,
+        }))
+    }
+
+    // for ID = Expression , Expression do Statement MoreStatements forend
+    fn for_loop(&mut self, id: usize) -> Option<Statement> {
+        let span = self.tree.span(id);
+        let variable = self.ident(self.child(id, 1));
+        let start = self.expr(self.child(id, 3));
+        let end = self.expr(self.child(id, 5));
+        let body = self.statements(self.child(id, 7), self.child(id, 8));
+        Some(Statement::For(For {
+            variable,
+            start: start?,
+            end: end?,
+            body: Box::new(self.single(body, span)),
+            span,
+        }))
+    }
+
+    // do Statement MoreStatements doend while Expression
+    fn do_while(&mut self, id: usize) -> Option<Statement> {
+        let span = self.tree.span(id);
+        let body = self.statements(self.child(id, 1), self.child(id, 2));
+        let condition = self.expr(self.child(id, 5));
+        Some(Statement::DoWhile(DoWhile {
+            body: Box::new(self.single(body, span)),
+            condition: condition?,
+            span,
+        }))
+    }
+
+    // begin Statement MoreStatements end
+    fn block(&mut self, id: usize) -> Option<Statement> {
+        let statements = self.statements(self.child(id, 1), self.child(id, 2));
+        Some(Statement::Block(Block {
+            statements,
+            span: self.tree.span(id),
+        }))
+    }
+
+    // input ( ID MoreIds )
+    fn input(&mut self, id: usize) -> Option<Statement> {
+        let targets = self.idents(self.child(id, 2), self.child(id, 3));
+        Some(Statement::Input(Input {
+            targets,
+            span: self.tree.span(id),
+        }))
+    }
+
+    // output ( Expression MoreExprs )
+    fn output(&mut self, id: usize) -> Option<Statement> {
+        let mut values = vec![self.expr(self.child(id, 2))];
+        let mut more = self.child(id, 3);
+        while let [COMMA, EXPR, MOREEXPRS] = self.shape(more).as_slice() {
+            values.push(self.expr(self.child(more, 1)));
+            more = self.child(more, 2);
+        }
+        let values = values.into_iter().collect::<Option<Vec<Expr>>>()?;
+        Some(Statement::Output(Output {
+            values,
+            span: self.tree.span(id),
+        }))
+    }
+
+    // Statement MoreStatements, skipping empty statements
+    //  the list is the body of a block or loop, one level inside it
+    fn statements(&mut self, first: usize, more: usize) -> Vec<Statement> {
+        let lowered = self.nested(&[first], |this| {
+            let mut statements: Vec<Statement> = this.statement(first).into_iter().collect();
+            let mut more = more;
+            while let [SEMICOLON, STATEMENT, MORESTATEMENTS] = this.shape(more).as_slice() {
+                statements.extend(this.statement(this.child(more, 1)));
+                more = this.child(more, 2);
+            }
+            Some(statements)
+        });
+        lowered.unwrap_or_default()
+    }
+
+    // if Expression then Statement ElsePart, from Statement or an `else if` ElseBody
+    fn conditional(&mut self, id: usize) -> Option<Statement> {
+        let span = self.tree.span(id);
+        let condition = self.expr(self.child(id, 1));
+        let then = self.child(id, 3);
+        let then_branch = self.nested(&[then], |this| this.statement(then));
+        let else_branch = self.else_part(self.child(id, 4));
+        Some(Statement::If(If {
+            condition: condition?,
+            then_branch: Box::new(self.single(then_branch.into_iter().collect(), span)),
+            else_branch: else_branch.map(Box::new),
+            span,
+        }))
+    }
+
+    // ElsePart -> else ElseBody | ϵ
+    //  an `else if` becomes an if in the else branch, ending the chain at one endif
+    fn else_part(&mut self, id: usize) -> Option<Statement> {
+        if self.shape(id).as_slice() != [ELSE, ELSE_BODY] {
+            return None;
+        }
+        let body = self.child(id, 1);
+        self.nested(&[body], |this| match this.shape(body).as_slice() {
+            [IF, EXPR, THEN, STATEMENT, ELSE_PART] => this.conditional(body),
+            _ => {
+                let statement = this.statement(this.child(body, 0));
+                Some(this.single(statement.into_iter().collect(), this.tree.span(body)))
+            }
+        })
+    }
+
+    //branches and loop bodies hold one statement, wrap anything else in a block
+    fn single(&self, mut statements: Vec<Statement>, span: Span) -> Statement {
+        if statements.len() == 1 {
+            statements.remove(0)
+        } else {
+            Statement::Block(Block { statements, span })
+        }
+    }
+
+    fn declaration(&mut self, id: usize) -> Declaration {
+        let ty = match self.shape(self.child(id, 0)).as_slice() {
+            [BOOL] => Type::Bool,
+            [FLOAT] => Type::Float,
+            _ => Type::Int,
+        };
+
+        Declaration {
+            ty,
+            names: self.idents(self.child(id, 1), self.child(id, 2)),
+            span: self.tree.span(id),
+        }
+    }
+
+    // ID MoreIds
+    fn idents(&self, first: usize, more: usize) -> Vec<Ident> {
+        let mut names = vec![self.ident(first)];
+        let mut more = more;
+        while let [COMMA, ID_NT, MOREIDS] = self.shape(more).as_slice() {
+            names.push(self.ident(self.child(more, 1)));
+            more = self.child(more, 2);
+        }
+        names
+    }
+
+    // Expression Assign, an assignment when Assign is not empty
+    fn expr_statement(&mut self, id: usize) -> Option<Statement> {
+        let lhs = self.expr(self.child(id, 0));
+        let assign = self.child(id, 1);
+        if self.shape(assign).as_slice() != [EQUAL, EXPR] {
+            return lhs.map(Statement::Expr);
+        }
+
+        let value = self.expr(self.child(assign, 1));
+        let target = match lhs? {
+            Expr {
+                kind: ExprKind::Var(ident),
+                ..
+            } => ident,
+            lhs => {
+                self.errors.push(CompileError::Syntax {
+                    location: Location::from(lhs.span),
+                    expected: vec!["identifier".to_string()],
+                    found: "expression".to_string(),
+                });
+                return None;
+            }
+        };
+
+        Some(Statement::Assign(Assign {
+            target,
+            value: value?,
+            span: self.tree.span(id),
+        }))
+    }
+
+    // Expression -> Conjunction ExpressionPrime
+    fn expr(&mut self, id: usize) -> Option<Expr> {
+        let links = self.chain(self.child(id, 1), &[OR]);
+        if links.is_empty() {
+            return self.conjunction(self.child(id, 0));
+        }
+        self.nested(&links, |this| {
+            let lhs = this.conjunction(this.child(id, 0));
+            this.expr_prime(lhs, this.child(id, 1))
+        })
+    }
+
+    //fold the or chain onto the left operand
+    //  the chain nests one ExpressionPrime per operator, so it is walked with a loop
+    fn expr_prime(&mut self, lhs: Option<Expr>, id: usize) -> Option<Expr> {
+        let mut folded = lhs;
+        let mut id = id;
+        while self.shape(id).first() == Some(&OR) {
+            let rhs = self.conjunction(self.child(id, 1));
+            folded = match (folded, rhs) {
+                (Some(lhs), Some(rhs)) => Some(Expr::logical(LogicalOp::Or, lhs, rhs)),
+                _ => None,
+            };
+            id = self.child(id, 2);
+        }
+        folded
+    }
+
+    // Conjunction -> Negation ConjunctionPrime
+    fn conjunction(&mut self, id: usize) -> Option<Expr> {
+        let links = self.chain(self.child(id, 1), &[AND]);
+        if links.is_empty() {
+            return self.negation(self.child(id, 0));
+        }
+        self.nested(&links, |this| {
+            let lhs = this.negation(this.child(id, 0));
+            this.conjunction_prime(lhs, this.child(id, 1))
+        })
+    }
+
+    fn conjunction_prime(&mut self, lhs: Option<Expr>, id: usize) -> Option<Expr> {
+        let mut folded = lhs;
+        let mut id = id;
+        while self.shape(id).first() == Some(&AND) {
+            let rhs = self.negation(self.child(id, 1));
+            folded = match (folded, rhs) {
+                (Some(lhs), Some(rhs)) => Some(Expr::logical(LogicalOp::And, lhs, rhs)),
+                _ => None,
+            };
+            id = self.child(id, 2);
+        }
+        folded
+    }
+
+    // Negation -> not Negation | Relation
+    fn negation(&mut self, id: usize) -> Option<Expr> {
+        match self.shape(id).as_slice() {
+            [NOT, NEGATION] => self.not(id),
+            _ => self.relation(self.child(id, 0)),
+        }
+    }
+
+    // Negation -> not Negation
+    fn not(&mut self, id: usize) -> Option<Expr> {
+        let inner = self.child(id, 1);
+        let operand = self.nested(&[self.child(id, 0)], |this| this.negation(inner))?;
+        Some(Expr {
+            span: self.tree.span(id),
+            kind: ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand: Box::new(operand),
+            },
+        })
+    }
+
+    // Relation -> Sum RelationPrime, relops do not chain
+    fn relation(&mut self, id: usize) -> Option<Expr> {
+        let tail = self.child(id, 1);
+        if self.shape(tail).as_slice() != [RELOP, SUM] {
+            return self.sum(self.child(id, 0));
+        }
+
+        let op = match self.shape(self.child(tail, 0)).as_slice() {
+            [LTHAN] => Relop::Less,
+            [LEQUAL] => Relop::LessEqual,
+            [EQUALTO] => Relop::Equal,
+            [NOTEQUAL] => Relop::NotEqual,
+            [GTHANEQUAL] => Relop::GreaterEqual,
+            _ => Relop::Greater,
+        };
+        self.nested(&[self.child(tail, 0)], |this| {
+            let lhs = this.sum(this.child(id, 0));
+            let rhs = this.sum(this.child(tail, 1));
+            Some(Expr::relop(op, lhs?, rhs?))
+        })
+    }
+
+    // Sum -> Term SumPrime
+    fn sum(&mut self, id: usize) -> Option<Expr> {
+        let links = self.chain(self.child(id, 1), &[PLUS, MINUS]);
+        if links.is_empty() {
+            return self.term(self.child(id, 0));
+        }
+        self.nested(&links, |this| {
+            let lhs = this.term(this.child(id, 0));
+            this.sum_prime(lhs, this.child(id, 1))
+        })
+    }
+
+    //fold the +/- chain onto the left operand so the operators associate left
+    fn sum_prime(&mut self, lhs: Option<Expr>, id: usize) -> Option<Expr> {
+        let mut folded = lhs;
+        let mut id = id;
+        loop {
+            let op = match self.shape(id).first() {
+                Some(PLUS) => BinaryOp::Add,
+                Some(MINUS) => BinaryOp::Sub,
+                _ => return folded,
+            };
+            let rhs = self.term(self.child(id, 1));
+            folded = match (folded, rhs) {
+                (Some(lhs), Some(rhs)) => Some(Expr::binary(op, lhs, rhs)),
+                _ => None,
+            };
+            id = self.child(id, 2);
+        }
+    }
+
+    // Term -> Factor TermPrime
+    fn term(&mut self, id: usize) -> Option<Expr> {
+        let links = self.chain(self.child(id, 1), &[MULT, DIV]);
+        if links.is_empty() {
+            return self.factor(self.child(id, 0));
+        }
+        self.nested(&links, |this| {
+            let lhs = this.factor(this.child(id, 0));
+            this.term_prime(lhs, this.child(id, 1))
+        })
+    }
+
+    fn term_prime(&mut self, lhs: Option<Expr>, id: usize) -> Option<Expr> {
+        let mut folded = lhs;
+        let mut id = id;
+        loop {
+            let op = match self.shape(id).first() {
+                Some(MULT) => BinaryOp::Mul,
+                Some(DIV) => BinaryOp::Div,
+                _ => return folded,
+            };
+            let rhs = self.factor(self.child(id, 1));
+            folded = match (folded, rhs) {
+                (Some(lhs), Some(rhs)) => Some(Expr::binary(op, lhs, rhs)),
+                _ => None,
+            };
+            id = self.child(id, 2);
+        }
+    }
+
+    // Factor -> - Factor | ( Expression ) | ID | num | real | true | false
+    fn factor(&mut self, id: usize) -> Option<Expr> {
+        match self.shape(id).as_slice() {
+            [MINUS, FACTOR] => self.negate(id),
+            //parentheses leave nothing in the tree, but lowering them still recurses
+            [L_PAREN, EXPR, R_PAREN] => {
+                let inner = self.child(id, 1);
+                self.nested(&[self.child(id, 0)], |this| this.expr(inner))
+            }
+            [ID_NT] => {
+                let ident = self.ident(self.child(id, 0));
+                Some(Expr {
+                    span: ident.span,
+                    kind: ExprKind::Var(ident),
+                })
+            }
+            [NUM] => self.number(self.child(id, 0), false),
+            [REAL] => self.real(self.child(id, 0)),
+            [TRUE] | [FALSE] => Some(Expr {
+                kind: ExprKind::Literal(Literal::Bool(self.shape(id)[0] == TRUE)),
+                span: self.tree.span(id),
+            }),
+            _ => None,
+        }
+    }
+
+    // Factor -> - Factor, where a minus in front of a literal folds into it
+    fn negate(&mut self, id: usize) -> Option<Expr> {
+        let span = self.tree.span(id);
+        let inner = self.child(id, 1);
+        //parsed with its sign so the most negative int is still a literal
+        if self.shape(inner).as_slice() == [NUM] {
+            let mut literal = self.number(self.child(inner, 0), true)?;
+            literal.span = span;
+            return Some(literal);
+        }
+
+        //an int that cannot be negated, the most negative one, is left to overflow
+        //  at run time
+        let operand = self.nested(&[self.child(id, 0)], |this| this.factor(inner))?;
+        let kind = match operand.kind {
+            ExprKind::Literal(Literal::Float(value)) => ExprKind::Literal(Literal::Float(-value)),
+            ExprKind::Literal(Literal::Int(value)) if value.checked_neg().is_some() => {
+                ExprKind::Literal(Literal::Int(-value))
+            }
+            _ => ExprKind::Unary {
+                op: UnaryOp::Neg,
+                operand: Box::new(operand),
+            },
+        };
+        Some(Expr { kind, span })
+    }
+
+    fn number(&mut self, id: usize, negative: bool) -> Option<Expr> {
+        let token = self.tree.nodes[id].token.as_ref()?;
+        let digits = match token.kind {
+            TokenKind::Integer(digits) => digits,
+            _ => return None,
+        };
+        let value = if negative {
+            -i128::from(digits)
+        } else {
+            i128::from(digits)
+        };
+        match i64::try_from(value) {
+            Ok(value) => Some(Expr {
+                kind: ExprKind::Literal(Literal::Int(value)),
+                span: token.span,
+            }),
+            Err(_) => {
+                self.errors.push(CompileError::Semantic {
+                    location: Location::from(token.span),
+                    message: format!("integer literal `{}` is too large", value),
+                    notes: Vec::new(),
+                });
+                None
+            }
+        }
+    }
+
+    fn real(&self, id: usize) -> Option<Expr> {
+        let token = self.tree.nodes[id].token.as_ref()?;
+        let value = match token.kind {
+            TokenKind::Real(value) => value,
+            _ => return None,
+        };
+        Some(Expr {
+            kind: ExprKind::Literal(Literal::Float(value)),
+            span: token.span,
+        })
+    }
+
+    // ID -> id
+    fn ident(&self, id: usize) -> Ident {
+        let token = self.tree.nodes[id]
+            .children
+            .first()
+            .and_then(|child| self.tree.nodes[*child].token.as_ref());
+
+        match token {
+            Some(TokenType {
+                kind: TokenKind::Identifier(name),
+                span,
+            }) => Ident {
+                name: Rc::clone(name),
+                span: *span,
+            },
+            _ => Ident {
+                name: Rc::from(""),
+                span: self.tree.span(id),
+            },
+        }
+    }
+}
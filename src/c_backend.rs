@@ -0,0 +1,429 @@
+//translation of a checked program into portable C99
+//  every variable becomes a typed local declared once at the top of main, so one
+//  declared in a loop keeps its value between passes like in the machine's
+//  memory, control flow maps onto C's own,
+//  input reads words with scanf and output prints with printf
+//  int arithmetic goes through small helpers that stop the program on overflow
+//  and division by zero, the same way the virtual machine does, instead of
+//  leaving it undefined
+//  variables are named after the Rat name and their memory address, `a` at 5000
+//  becomes `a_5000`, so they never clash with C keywords or the helpers
+use crate::ast::{
+    BinaryOp, Expr, ExprKind, Ident, Literal, LogicalOp, Program, Relop, Statement, Type, UnaryOp,
+};
+use crate::error::{CompileError, Location};
+use crate::symbol_table::{Symbol, SymbolTable};
+
+//everything ahead of main, the helpers are static inline so unused ones cost nothing
+const PRELUDE: &str = r#"#include <errno.h>
+#include <limits.h>
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static inline void rat_fail(const char *message)
+{
+    fflush(stdout);
+    fprintf(stderr, "runtime error: %s\n", message);
+    exit(1);
+}
+
+static inline long long rat_add(long long a, long long b)
+{
+    if ((b > 0 && a > LLONG_MAX - b) || (b < 0 && a < LLONG_MIN - b))
+        rat_fail("integer overflow");
+    return a + b;
+}
+
+static inline long long rat_sub(long long a, long long b)
+{
+    if ((b < 0 && a > LLONG_MAX + b) || (b > 0 && a < LLONG_MIN + b))
+        rat_fail("integer overflow");
+    return a - b;
+}
+
+static inline long long rat_mul(long long a, long long b)
+{
+    if (a > 0 ? (b > 0 ? a > LLONG_MAX / b : b < LLONG_MIN / a)
+              : (b > 0 ? a < LLONG_MIN / b : a != 0 && b < LLONG_MAX / a))
+        rat_fail("integer overflow");
+    return a * b;
+}
+
+static inline long long rat_div(long long a, long long b)
+{
+    if (b == 0)
+        rat_fail("division by zero");
+    if (a == LLONG_MIN && b == -1)
+        rat_fail("integer overflow");
+    return a / b;
+}
+
+static inline double rat_fdiv(double a, double b)
+{
+    if (b == 0.0)
+        rat_fail("division by zero");
+    return a / b;
+}
+
+/* the next whitespace separated word of input */
+static inline void rat_word(char *word)
+{
+    if (scanf("%63s", word) != 1)
+        rat_fail("input ended before a value could be read");
+}
+
+static inline int rat_parse_int(const char *word, long long *value)
+{
+    char *end;
+    errno = 0;
+    *value = strtoll(word, &end, 10);
+    return end != word && *end == '\0' && errno == 0;
+}
+
+static inline int rat_parse_float(const char *word, double *value)
+{
+    char *end;
+    *value = strtod(word, &end);
+    return end != word && *end == '\0' && *value - *value == 0.0;
+}
+
+static inline long long rat_input_int(void)
+{
+    char word[64];
+    long long value;
+    double real;
+    rat_word(word);
+    if (rat_parse_int(word, &value))
+        return value;
+    if (rat_parse_float(word, &real))
+        rat_fail("cannot store a real number in an int variable");
+    rat_fail("input expected a number");
+    return 0;
+}
+
+static inline double rat_input_float(void)
+{
+    char word[64];
+    long long value;
+    double real;
+    rat_word(word);
+    if (rat_parse_int(word, &value))
+        return (double)value;
+    if (!rat_parse_float(word, &real))
+        rat_fail("input expected a number");
+    return real;
+}
+
+/* the fewest digits that read back as the same value, printed like the stack
+   machine prints them: 2.0, 0.25, and 1e-5 or 1.5e20 for values far from 1,
+   or inf, -inf and NaN once a value overflows */
+static inline void rat_print_float(double value)
+{
+    char text[64];
+    char *from;
+    char *to;
+    double size = value < 0 ? -value : value;
+    int fixed = size == 0.0 || (size >= 1e-4 && size < 1e16);
+    int precision;
+    if (isnan(value)) {
+        printf("NaN\n");
+        return;
+    }
+    if (!isfinite(value)) {
+        printf(value < 0 ? "-inf\n" : "inf\n");
+        return;
+    }
+    for (precision = fixed ? 1 : 0; precision <= 24; precision++) {
+        snprintf(text, sizeof text, fixed ? "%.*f" : "%.*e", precision, value);
+        if (strtod(text, NULL) == value)
+            break;
+    }
+    if (!fixed) {
+        /* drop the exponent's plus sign and leading zeros */
+        from = strchr(text, 'e') + 1;
+        to = from;
+        if (*from == '-')
+            from = ++to;
+        else if (*from == '+')
+            from++;
+        while (*from == '0' && from[1] != '\0')
+            from++;
+        memmove(to, from, strlen(from) + 1);
+    }
+    printf("%s\n", text);
+}
+
+int main(void)
+{
+"#;
+
+//translate a checked program into a complete C source file
+//  variables are named after what SymbolTable::resolve finds for them
+pub fn generate(program: &Program, symbols: &SymbolTable) -> Result<String, Vec<CompileError>> {
+    let mut generator = Generator {
+        symbols,
+        out: PRELUDE.to_string(),
+        depth: 1,
+        errors: Vec::new(),
+    };
+    //variables start at zero like the machine's memory
+    for symbol in symbols.symbols() {
+        let line = format!("{} {} = 0;", c_type(symbol.ty), c_name(symbol));
+        generator.line(&line);
+    }
+    for statement in program.statements.iter() {
+        generator.body(statement);
+    }
+    generator.line("return 0;");
+    generator.out.push_str("}\n");
+
+    if generator.errors.is_empty() {
+        Ok(generator.out)
+    } else {
+        Err(generator.errors)
+    }
+}
+
+struct Generator<'a> {
+    symbols: &'a SymbolTable,
+    out: String,
+    depth: usize, // indentation level of the next line
+    errors: Vec<CompileError>,
+}
+
+impl<'a> Generator<'a> {
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            //every variable is already declared at the top of main
+            Statement::Declaration(_) => {}
+
+            //C converts an int stored in a double by itself
+            Statement::Assign(assign) => {
+                let line = format!(
+                    "{} = {};",
+                    self.name(&assign.target),
+                    self.expr(&assign.value)
+                );
+                self.line(&line);
+            }
+
+            //an if in the else branch continues the chain as `else if`
+            Statement::If(i) => {
+                let line = format!("if {} {{", self.condition(&i.condition));
+                self.line(&line);
+                self.nested(&i.then_branch);
+                let mut else_branch = i.else_branch.as_deref();
+                while let Some(Statement::If(chained)) = else_branch {
+                    let line = format!("}} else if {} {{", self.condition(&chained.condition));
+                    self.line(&line);
+                    self.nested(&chained.then_branch);
+                    else_branch = chained.else_branch.as_deref();
+                }
+                if let Some(else_branch) = else_branch {
+                    self.line("} else {");
+                    self.nested(else_branch);
+                }
+                self.line("}");
+            }
+
+            Statement::While(w) => {
+                let line = format!("while {} {{", self.condition(&w.condition));
+                self.line(&line);
+                self.nested(&w.body);
+                self.line("}");
+            }
+
+            //C tests the condition before each pass, which keeps ast::For's rule for end
+            Statement::For(f) => {
+                let variable = self.name(&f.variable);
+                let line = format!(
+                    "for ({0} = {1}; {0} <= {2}; {0} = rat_add({0}, 1LL)) {{",
+                    variable,
+                    self.expr(&f.start),
+                    self.expr(&f.end)
+                );
+                self.line(&line);
+                self.nested(&f.body);
+                self.line("}");
+            }
+
+            Statement::DoWhile(d) => {
+                self.line("do {");
+                self.nested(&d.body);
+                let line = format!("}} while {};", self.condition(&d.condition));
+                self.line(&line);
+            }
+
+            Statement::Block(_) => {
+                self.line("{");
+                self.nested(statement);
+                self.line("}");
+            }
+
+            Statement::Input(input) => {
+                for target in input.targets.iter() {
+                    let read = match self.type_of_name(target) {
+                        Type::Float => "rat_input_float()",
+                        _ => "rat_input_int()",
+                    };
+                    let line = format!("{} = {};", self.name(target), read);
+                    self.line(&line);
+                }
+            }
+
+            Statement::Output(output) => {
+                for value in output.values.iter() {
+                    let text = self.expr(value);
+                    let line = match self.type_of(value) {
+                        Type::Int => format!("printf(\"%lld\\n\", {});", text),
+                        Type::Float => format!("rat_print_float({});", text),
+                        Type::Bool => format!("printf(\"%d\\n\", {});", text),
+                    };
+                    self.line(&line);
+                }
+            }
+
+            //nothing runs for a bare expression, as in the stack machine code
+            Statement::Expr(_) => {}
+        }
+    }
+
+    //a branch or loop body one level in, a block's braces are the ones around it
+    fn nested(&mut self, statement: &Statement) {
+        self.depth += 1;
+        self.body(statement);
+        self.depth -= 1;
+    }
+
+    fn body(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Block(b) => {
+                for statement in b.statements.iter() {
+                    self.statement(statement);
+                }
+            }
+            _ => self.statement(statement),
+        }
+    }
+
+    //a condition in the parentheses C wants, without doubling those of an operator
+    fn condition(&mut self, condition: &Expr) -> String {
+        let text = self.expr(condition);
+        match condition.kind {
+            ExprKind::Relop { .. } | ExprKind::Logical { .. } | ExprKind::Unary { .. } => text,
+            _ => format!("({})", text),
+        }
+    }
+
+    //C source for an expression, every operator is parenthesized
+    fn expr(&mut self, expr: &Expr) -> String {
+        match &expr.kind {
+            //the most negative long long cannot be written as a literal
+            ExprKind::Literal(Literal::Int(i64::MIN)) => "(-9223372036854775807LL - 1)".to_string(),
+            ExprKind::Literal(Literal::Int(value)) if *value < 0 => format!("({}LL)", value),
+            ExprKind::Literal(Literal::Int(value)) => format!("{}LL", value),
+            ExprKind::Literal(Literal::Float(value)) if *value < 0.0 => format!("({:?})", value),
+            ExprKind::Literal(Literal::Float(value)) => format!("{:?}", value),
+            ExprKind::Literal(Literal::Bool(value)) => (*value as i32).to_string(),
+            ExprKind::Var(ident) => self.name(ident),
+            ExprKind::Binary { op, lhs, rhs } => {
+                let float = self.type_of(expr) == Type::Float;
+                let (lhs, rhs) = (self.expr(lhs), self.expr(rhs));
+                match (op, float) {
+                    (BinaryOp::Add, false) => format!("rat_add({}, {})", lhs, rhs),
+                    (BinaryOp::Sub, false) => format!("rat_sub({}, {})", lhs, rhs),
+                    (BinaryOp::Mul, false) => format!("rat_mul({}, {})", lhs, rhs),
+                    (BinaryOp::Div, false) => format!("rat_div({}, {})", lhs, rhs),
+                    (BinaryOp::Div, true) => format!("rat_fdiv({}, {})", lhs, rhs),
+                    (op, true) => format!("({} {} {})", lhs, op, rhs),
+                }
+            }
+            ExprKind::Relop { op, lhs, rhs } => {
+                let op = match op {
+                    Relop::NotEqual => "!=".to_string(),
+                    op => op.to_string(),
+                };
+                format!("({} {} {})", self.expr(lhs), op, self.expr(rhs))
+            }
+            ExprKind::Logical { op, lhs, rhs } => {
+                let op = match op {
+                    LogicalOp::And => "&&",
+                    LogicalOp::Or => "||",
+                };
+                format!("({} {} {})", self.expr(lhs), op, self.expr(rhs))
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => format!("(!{})", self.expr(operand)),
+            ExprKind::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => match self.type_of(operand) {
+                Type::Float => format!("(-{})", self.expr(operand)),
+                _ => format!("rat_sub(0LL, {})", self.expr(operand)),
+            },
+        }
+    }
+
+    //type of a checked expression, following the semantic pass's promotion rules
+    fn type_of(&self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Int(_)) => Type::Int,
+            ExprKind::Literal(Literal::Float(_)) => Type::Float,
+            ExprKind::Literal(Literal::Bool(_)) => Type::Bool,
+            ExprKind::Var(ident) => self.type_of_name(ident),
+            ExprKind::Binary { lhs, rhs, .. } => self.type_of(lhs).promote(self.type_of(rhs)),
+            ExprKind::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => self.type_of(operand),
+            ExprKind::Relop { .. } | ExprKind::Logical { .. } | ExprKind::Unary { .. } => {
+                Type::Bool
+            }
+        }
+    }
+
+    fn type_of_name(&self, name: &Ident) -> Type {
+        self.symbols
+            .resolve(name)
+            .map_or(Type::Int, |symbol| symbol.ty)
+    }
+
+    //C name of a variable, an error when resolve() has no symbol for it
+    fn name(&mut self, name: &Ident) -> String {
+        match self.symbols.resolve(name) {
+            Some(symbol) => c_name(symbol),
+            None => {
+                self.errors.push(CompileError::Semantic {
+                    location: Location::from(name.span),
+                    message: format!("no C variable for `{}`", name.name),
+                    notes: Vec::new(),
+                });
+                String::new()
+            }
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+}
+
+fn c_name(symbol: &Symbol) -> String {
+    format!("{}_{}", symbol.name.replace('$', "_"), symbol.address)
+}
+
+fn c_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Int => "long long",
+        Type::Float => "double",
+        Type::Bool => "int",
+    }
+}
@@ -0,0 +1,210 @@
+//command line handling for the compiler binary
+//  usage: cpsc_323_compiler_project [run | interpret] [OPTIONS] <INPUT>...
+//  with no arguments at all the program falls back to prompting for the paths
+use cpsc_323_compiler_project::diagnostics::ColorChoice;
+use cpsc_323_compiler_project::parser;
+use cpsc_323_compiler_project::symbol_table;
+use cpsc_323_compiler_project::Stage;
+
+pub const USAGE: &str = "\
+Usage: cpsc_323_compiler_project [OPTIONS] <INPUT>...
+       cpsc_323_compiler_project run [OPTIONS] <INPUT>...
+       cpsc_323_compiler_project interpret [OPTIONS] <INPUT>...
+
+Commands:
+  run                      Compile the input(s) and execute the generated code
+  interpret                Check the input(s) and evaluate the syntax tree directly
+
+Arguments:
+  <INPUT>...               Source file(s) to compile, - reads standard input
+
+Options:
+  -o, --output <FILE>      Append results to FILE instead of printing to stdout,
+                           with --emit c FILE is replaced
+  -s, --stage <STAGE>      Stage to run: lex, parse, symbols or code [default: code]
+      --error-limit <N>    Stop after N syntax errors, 0 for no limit [default: 20]
+      --color <WHEN>       Color error messages: auto, always or never [default: auto]
+      --emit <FORMAT>      Write the stage results or C source: stages or c [default: stages]
+                           c takes a single input
+      --base-address <N>   Memory address of the first variable [default: 5000]
+      --stdin <FILE>       With run or interpret, read input from FILE instead of the terminal
+      --stdout <FILE>      With run or interpret, write output to FILE instead of the terminal
+  -h, --help               Print this help
+
+Run without any arguments to be prompted for the input and output paths.";
+
+//map a --stage value onto the library's pipeline stage
+fn stage_from_name(name: &str) -> Option<Stage> {
+    match name {
+        "lex" => Some(Stage::Lex),
+        "parse" => Some(Stage::Parse),
+        "symbols" => Some(Stage::Symbols),
+        "code" => Some(Stage::Code),
+        _ => None,
+    }
+}
+
+//what compiling writes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Emit {
+    Stages, // the listings of every stage up to --stage
+    C,      // a C99 translation of each checked program
+}
+
+//map an --emit value onto what to write
+fn emit_from_name(name: &str) -> Option<Emit> {
+    match name {
+        "stages" => Some(Emit::Stages),
+        "c" => Some(Emit::C),
+        _ => None,
+    }
+}
+
+//what to do with the inputs
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Compile,   // write the requested stage's results
+    Run,       // execute the generated code
+    Interpret, // evaluate the syntax tree without generating code
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub command: Command,
+    pub inputs: Vec<String>,
+    pub output: Option<String>,
+    pub stage: Stage,
+    pub emit: Emit,
+    pub color: ColorChoice,
+    pub error_limit: usize,
+    pub base_address: usize,
+    pub stdin: Option<String>,  // file the program's STDIN reads from
+    pub stdout: Option<String>, // file the program's STDOUT writes to
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            command: Command::Compile,
+            inputs: Vec::new(),
+            output: None,
+            stage: Stage::Code,
+            emit: Emit::Stages,
+            color: ColorChoice::Auto,
+            error_limit: parser::DEFAULT_ERROR_LIMIT,
+            base_address: symbol_table::DEFAULT_BASE_ADDRESS,
+            stdin: None,
+            stdout: None,
+            help: false,
+        }
+    }
+}
+
+//turn the arguments after the program name into options
+//  Err holds a message suitable for printing above the usage text
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut iter = args.iter().peekable();
+
+    //a leading "run" or "interpret" picks the subcommand
+    let command = match iter.peek().map(|arg| arg.as_str()) {
+        Some("run") => Some(Command::Run),
+        Some("interpret") => Some(Command::Interpret),
+        _ => None,
+    };
+    if let Some(command) = command {
+        options.command = command;
+        iter.next();
+    }
+
+    while let Some(arg) = iter.next() {
+        //split "--flag=value" so both spellings are accepted
+        let (flag, inline_value) = match arg.find('=') {
+            Some(pos) if arg.starts_with("--") => (&arg[..pos], Some(arg[pos + 1..].to_string())),
+            _ => (arg.as_str(), None),
+        };
+
+        match flag {
+            "-h" | "--help" => options.help = true,
+
+            "-o" | "--output" => {
+                options.output = Some(take_value(flag, inline_value, &mut iter)?);
+            }
+
+            "-s" | "--stage" => {
+                let name = take_value(flag, inline_value, &mut iter)?;
+                options.stage =
+                    stage_from_name(&name).ok_or_else(|| format!("unknown stage '{}'", name))?;
+            }
+
+            "--emit" => {
+                let name = take_value(flag, inline_value, &mut iter)?;
+                options.emit =
+                    emit_from_name(&name).ok_or_else(|| format!("unknown format '{}'", name))?;
+            }
+
+            "--error-limit" => {
+                let value = take_value(flag, inline_value, &mut iter)?;
+                options.error_limit = value
+                    .parse()
+                    .map_err(|_| format!("invalid error limit '{}'", value))?;
+            }
+
+            "--base-address" => {
+                let value = take_value(flag, inline_value, &mut iter)?;
+                options.base_address = value
+                    .parse()
+                    .map_err(|_| format!("invalid base address '{}'", value))?;
+            }
+
+            "--stdin" => options.stdin = Some(take_value(flag, inline_value, &mut iter)?),
+
+            "--stdout" => options.stdout = Some(take_value(flag, inline_value, &mut iter)?),
+
+            "--color" => {
+                let name = take_value(flag, inline_value, &mut iter)?;
+                options.color = ColorChoice::from_name(&name)
+                    .ok_or_else(|| format!("unknown color choice '{}'", name))?;
+            }
+
+            //everything after "--" is an input, even if it looks like a flag
+            "--" => options.inputs.extend(iter.by_ref().cloned()),
+
+            _ if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option '{}'", arg));
+            }
+
+            _ => options.inputs.push(arg.to_string()),
+        }
+    }
+
+    if options.inputs.is_empty() && !options.help {
+        return Err("no input files given".to_string());
+    }
+
+    //each translation is a whole C program with its own main, two cannot share a file
+    if options.command == Command::Compile && options.emit == Emit::C && options.inputs.len() > 1 {
+        return Err("--emit c takes a single input".to_string());
+    }
+
+    Ok(options)
+}
+
+//get the value for a flag either from "--flag=value" or from the next argument
+fn take_value<'a, I>(
+    flag: &str,
+    inline_value: Option<String>,
+    iter: &mut I,
+) -> Result<String, String>
+where
+    I: Iterator<Item = &'a String>,
+{
+    match inline_value {
+        Some(value) => Ok(value),
+        None => iter
+            .next()
+            .cloned()
+            .ok_or_else(|| format!("option '{}' needs a value", flag)),
+    }
+}
@@ -0,0 +1,319 @@
+//code generation for the course's stack machine
+//  expressions push their operands and leave the result on the stack, variables
+//  live at the memory addresses the symbol table handed out
+//  jump targets are instruction numbers, counting from 1 like the listing
+//  ints and floats share the instructions, the machine promotes mixed operands
+//  and POPM converts an int stored into a float variable
+use crate::ast::{
+    BinaryOp, Expr, ExprKind, Ident, Literal, LogicalOp, Program, Relop, Statement, UnaryOp,
+};
+use crate::error::{CompileError, Location};
+use crate::symbol_table::SymbolTable;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Instruction {
+    Pushi(i64),   // push an integer
+    Pushf(f64),   // push a real number
+    Pushm(usize), // push the value at an address
+    Popm(usize),  // pop into an address
+    Stdout,       // pop and print
+    Stdin,        // read and push
+    Add,          // pop b, pop a, push a + b
+    Sub,          // pop b, pop a, push a - b
+    Mul,          // pop b, pop a, push a * b
+    Div,          // pop b, pop a, push a / b
+    Grt,          // pop b, pop a, push 1 when a > b else 0
+    Les,          // a < b
+    Equ,          // a == b
+    Neq,          // a <> b
+    Geq,          // a >= b
+    Leq,          // a <= b
+    Jumpz(usize), // pop and jump to the instruction when the value is 0
+    Jump(usize),  // jump to the instruction
+    Label,        // start of a loop, does nothing
+}
+
+impl Instruction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instruction::Pushi(_) => "PUSHI",
+            Instruction::Pushf(_) => "PUSHF",
+            Instruction::Pushm(_) => "PUSHM",
+            Instruction::Popm(_) => "POPM",
+            Instruction::Stdout => "STDOUT",
+            Instruction::Stdin => "STDIN",
+            Instruction::Add => "ADD",
+            Instruction::Sub => "SUB",
+            Instruction::Mul => "MUL",
+            Instruction::Div => "DIV",
+            Instruction::Grt => "GRT",
+            Instruction::Les => "LES",
+            Instruction::Equ => "EQU",
+            Instruction::Neq => "NEQ",
+            Instruction::Geq => "GEQ",
+            Instruction::Leq => "LEQ",
+            Instruction::Jumpz(_) => "JUMPZ",
+            Instruction::Jump(_) => "JUMP",
+            Instruction::Label => "LABEL",
+        }
+    }
+
+    //the operand as it appears in the listing, if there is one
+    pub fn operand(&self) -> Option<String> {
+        match self {
+            Instruction::Pushi(value) => Some(value.to_string()),
+            Instruction::Pushf(value) => Some(format!("{:?}", value)),
+            Instruction::Pushm(address) | Instruction::Popm(address) => Some(address.to_string()),
+            Instruction::Jumpz(target) | Instruction::Jump(target) => Some(target.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operand() {
+            Some(operand) => write!(f, "{:<8}{}", self.name(), operand),
+            None => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+//translate a checked program, addresses come from SymbolTable::resolve
+pub fn generate(
+    program: &Program,
+    symbols: &SymbolTable,
+) -> Result<Vec<Instruction>, Vec<CompileError>> {
+    let mut generator = Generator {
+        symbols,
+        code: Vec::new(),
+        errors: Vec::new(),
+    };
+    for statement in program.statements.iter() {
+        generator.statement(statement);
+    }
+
+    if generator.errors.is_empty() {
+        Ok(generator.code)
+    } else {
+        Err(generator.errors)
+    }
+}
+
+struct Generator<'a> {
+    symbols: &'a SymbolTable,
+    code: Vec<Instruction>,
+    errors: Vec<CompileError>,
+}
+
+impl<'a> Generator<'a> {
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            //memory is handed out by the symbol table, nothing to run
+            Statement::Declaration(_) => {}
+
+            Statement::Assign(assign) => {
+                self.expr(&assign.value);
+                let address = self.address(&assign.target);
+                self.emit(Instruction::Popm(address));
+            }
+
+            // cond; JUMPZ else; then; JUMP end; else: ...; end:
+            Statement::If(i) => {
+                self.expr(&i.condition);
+                let jump_to_else = self.emit(Instruction::Jumpz(0));
+                self.statement(&i.then_branch);
+                match &i.else_branch {
+                    Some(else_branch) => {
+                        let jump_to_end = self.emit(Instruction::Jump(0));
+                        self.patch(jump_to_else);
+                        self.statement(else_branch);
+                        self.patch(jump_to_end);
+                    }
+                    None => self.patch(jump_to_else),
+                }
+            }
+
+            // start: LABEL; cond; JUMPZ end; body; JUMP start; end:
+            Statement::While(w) => {
+                let start = self.emit(Instruction::Label);
+                self.expr(&w.condition);
+                let jump_to_end = self.emit(Instruction::Jumpz(0));
+                self.statement(&w.body);
+                self.emit(Instruction::Jump(start));
+                self.patch(jump_to_end);
+            }
+
+            // start; POPM i; top: LABEL; PUSHM i; end; LEQ; JUMPZ exit; body;
+            //  PUSHM i; PUSHI 1; ADD; POPM i; JUMP top; exit:
+            //  end comes after top, as ast::For needs
+            Statement::For(f) => {
+                let address = self.address(&f.variable);
+                self.expr(&f.start);
+                self.emit(Instruction::Popm(address));
+                let top = self.emit(Instruction::Label);
+                self.emit(Instruction::Pushm(address));
+                self.expr(&f.end);
+                self.emit(Instruction::Leq);
+                let jump_to_exit = self.emit(Instruction::Jumpz(0));
+                self.statement(&f.body);
+                self.emit(Instruction::Pushm(address));
+                self.emit(Instruction::Pushi(1));
+                self.emit(Instruction::Add);
+                self.emit(Instruction::Popm(address));
+                self.emit(Instruction::Jump(top));
+                self.patch(jump_to_exit);
+            }
+
+            // top: LABEL; body; cond; JUMPZ exit; JUMP top; exit:
+            Statement::DoWhile(d) => {
+                let top = self.emit(Instruction::Label);
+                self.statement(&d.body);
+                self.expr(&d.condition);
+                let jump_to_exit = self.emit(Instruction::Jumpz(0));
+                self.emit(Instruction::Jump(top));
+                self.patch(jump_to_exit);
+            }
+
+            Statement::Block(b) => {
+                for statement in b.statements.iter() {
+                    self.statement(statement);
+                }
+            }
+
+            //each value read is stored straight away
+            Statement::Input(input) => {
+                for target in input.targets.iter() {
+                    self.emit(Instruction::Stdin);
+                    let address = self.address(target);
+                    self.emit(Instruction::Popm(address));
+                }
+            }
+
+            Statement::Output(output) => {
+                for value in output.values.iter() {
+                    self.expr(value);
+                    self.emit(Instruction::Stdout);
+                }
+            }
+
+            //expressions have no side effects and the machine has no pop,
+            //  so a bare expression statement generates nothing
+            Statement::Expr(_) => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Int(value)) => {
+                self.emit(Instruction::Pushi(*value));
+            }
+            ExprKind::Literal(Literal::Float(value)) => {
+                self.emit(Instruction::Pushf(*value));
+            }
+            //true is 1 and false is 0, the same values the comparisons push
+            ExprKind::Literal(Literal::Bool(value)) => {
+                self.emit(Instruction::Pushi(*value as i64));
+            }
+            ExprKind::Var(ident) => {
+                let address = self.address(ident);
+                self.emit(Instruction::Pushm(address));
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.expr(lhs);
+                self.expr(rhs);
+                self.emit(match op {
+                    BinaryOp::Add => Instruction::Add,
+                    BinaryOp::Sub => Instruction::Sub,
+                    BinaryOp::Mul => Instruction::Mul,
+                    BinaryOp::Div => Instruction::Div,
+                });
+            }
+            ExprKind::Relop { op, lhs, rhs } => {
+                self.expr(lhs);
+                self.expr(rhs);
+                self.emit(match op {
+                    Relop::Less => Instruction::Les,
+                    Relop::LessEqual => Instruction::Leq,
+                    Relop::Equal => Instruction::Equ,
+                    Relop::NotEqual => Instruction::Neq,
+                    Relop::GreaterEqual => Instruction::Geq,
+                    Relop::Greater => Instruction::Grt,
+                });
+            }
+            //short circuit, the right side only runs when it decides the result
+            //  and: lhs; JUMPZ false; rhs; JUMP end; false: PUSHI 0; end:
+            //  or:  lhs; JUMPZ right; PUSHI 1; JUMP end; right: rhs; end:
+            ExprKind::Logical { op, lhs, rhs } => {
+                self.expr(lhs);
+                let jump_on_false = self.emit(Instruction::Jumpz(0));
+                let jump_to_end = match op {
+                    LogicalOp::And => {
+                        self.expr(rhs);
+                        let jump_to_end = self.emit(Instruction::Jump(0));
+                        self.patch(jump_on_false);
+                        self.emit(Instruction::Pushi(0));
+                        jump_to_end
+                    }
+                    LogicalOp::Or => {
+                        self.emit(Instruction::Pushi(1));
+                        let jump_to_end = self.emit(Instruction::Jump(0));
+                        self.patch(jump_on_false);
+                        self.expr(rhs);
+                        jump_to_end
+                    }
+                };
+                self.patch(jump_to_end);
+            }
+            // operand; PUSHI 0; EQU
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => {
+                self.expr(operand);
+                self.emit(Instruction::Pushi(0));
+                self.emit(Instruction::Equ);
+            }
+            // PUSHI 0; operand; SUB, a float operand promotes the 0
+            ExprKind::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => {
+                self.emit(Instruction::Pushi(0));
+                self.expr(operand);
+                self.emit(Instruction::Sub);
+            }
+        }
+    }
+
+    //address of a name, reported as an error when the semantic pass never saw it
+    fn address(&mut self, name: &Ident) -> usize {
+        match self.symbols.resolve(name) {
+            Some(symbol) => symbol.address,
+            None => {
+                self.errors.push(CompileError::Semantic {
+                    location: Location::from(name.span),
+                    message: format!("no memory address for `{}`", name.name),
+                    notes: Vec::new(),
+                });
+                0
+            }
+        }
+    }
+
+    //append an instruction and return its number
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code.push(instruction);
+        self.code.len()
+    }
+
+    //point an earlier jump at the next instruction to be emitted
+    fn patch(&mut self, number: usize) {
+        let target = self.code.len() + 1;
+        match &mut self.code[number - 1] {
+            Instruction::Jumpz(to) | Instruction::Jump(to) => *to = target,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//render compile errors the way rustc does, with the source line and carets
+//
+//  error: expected `;` or `end`, found `x`
+//   --> sample.txt:6:10
+//    |
+//  6 | input(a) x
+//    |          ^
+use crate::error::{CompileError, Note};
+use crate::tokens::Span;
+use std::io::IsTerminal;
+
+//width a tab is expanded to when printing source lines
+const TAB_WIDTH: usize = 4;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const GREEN: &str = "\x1b[1;32m";
+
+//when to use terminal colors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    Auto, // only when stderr is a terminal and NO_COLOR is not set
+}
+
+impl ColorChoice {
+    pub fn from_name(name: &str) -> Option<ColorChoice> {
+        match name {
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            "auto" => Some(ColorChoice::Auto),
+            _ => None,
+        }
+    }
+
+    pub fn use_color(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    pub fn new(choice: ColorChoice) -> Renderer {
+        Renderer {
+            color: choice.use_color(),
+        }
+    }
+
+    //plain text, for log files
+    pub fn plain() -> Renderer {
+        Renderer { color: false }
+    }
+
+    //turn one error into a block of text followed by a blank line
+    //  source is the text the error's spans point into
+    pub fn render(&self, error: &CompileError, source: &str) -> String {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "{}: {}\n",
+            self.paint(RED, "error"),
+            self.paint(BOLD, &error.message())
+        ));
+
+        let location = match error.location() {
+            Some(location) => location,
+            None => return out + "\n",
+        };
+        let file = location.file.as_deref().unwrap_or("<source>");
+
+        //the gutter has to fit the widest line number shown
+        let widest = error
+            .notes()
+            .iter()
+            .filter_map(|note| note.span)
+            .map(|span| span.start.line)
+            .fold(location.line(), usize::max);
+        let gutter = " ".repeat(widest.to_string().len());
+
+        self.snippet(&mut out, source, file, location.span, &gutter, RED, '^');
+
+        for note in error.notes() {
+            self.note(&mut out, source, file, note, &gutter);
+        }
+        out.push('\n');
+        out
+    }
+
+    fn note(&self, out: &mut String, source: &str, file: &str, note: &Note, gutter: &str) {
+        match note.span {
+            Some(span) => {
+                out.push_str(&format!(
+                    "{}: {}\n",
+                    self.paint(GREEN, "note"),
+                    note.message
+                ));
+                self.snippet(out, source, file, span, gutter, GREEN, '-');
+            }
+            None => out.push_str(&format!(
+                "{} {} {}: {}\n",
+                gutter,
+                self.paint(BLUE, "="),
+                self.paint(BOLD, "note"),
+                note.message
+            )),
+        }
+    }
+
+    //print the line a span starts on and underline the span
+    //  an empty source, as for input streamed from stdin and not kept, gets only
+    //  the location
+    #[allow(clippy::too_many_arguments)]
+    fn snippet(
+        &self,
+        out: &mut String,
+        source: &str,
+        file: &str,
+        span: Span,
+        gutter: &str,
+        color: &str,
+        mark: char,
+    ) {
+        let line_number = span.start.line;
+        let line_text = source.lines().nth(line_number - 1).unwrap_or("");
+        let line_text = line_text.trim_end_matches('\r');
+
+        out.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            self.paint(BLUE, "-->"),
+            file,
+            line_number,
+            span.start.column
+        ));
+        if source.is_empty() {
+            return;
+        }
+        out.push_str(&format!("{} {}\n", gutter, self.paint(BLUE, "|")));
+        out.push_str(&format!(
+            "{} {} {}\n",
+            self.paint(
+                BLUE,
+                &format!("{:>width$}", line_number, width = gutter.len())
+            ),
+            self.paint(BLUE, "|"),
+            expand_tabs(line_text)
+        ));
+
+        //columns are characters, spans running onto later lines stop at the line end
+        let chars: Vec<char> = line_text.chars().collect();
+        let start = (span.start.column - 1).min(chars.len());
+        let end = if span.end.line == span.start.line {
+            (span.end.column - 1).clamp(start, chars.len())
+        } else {
+            chars.len()
+        };
+
+        let padding = display_width(&chars[..start]);
+        let length = display_width(&chars[start..end]).max(1);
+        let marks = mark.to_string().repeat(length);
+        out.push_str(&format!(
+            "{} {} {}{}\n",
+            gutter,
+            self.paint(BLUE, "|"),
+            " ".repeat(padding),
+            self.paint(color, &marks)
+        ));
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+//render a list of errors one after the other
+pub fn render_all(renderer: &Renderer, errors: &[CompileError], source: &str) -> String {
+    errors
+        .iter()
+        .map(|error| renderer.render(error, source))
+        .collect()
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+fn display_width(chars: &[char]) -> usize {
+    chars
+        .iter()
+        .map(|c| if *c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
@@ -0,0 +1,199 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::tokens::Span;
+
+//where in the source something happened
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Location {
+    pub file: Option<String>,
+    pub span: Span,
+}
+
+impl Location {
+    pub fn line(&self) -> usize {
+        self.span.start.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.span.start.column
+    }
+}
+
+impl From<Span> for Location {
+    fn from(span: Span) -> Location {
+        Location { file: None, span }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}:{}", self.line(), self.column())
+    }
+}
+
+//extra context attached to a diagnostic, such as where a name was declared
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Note {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Note {
+    pub fn new(message: &str) -> Note {
+        Note {
+            message: message.to_string(),
+            span: None,
+        }
+    }
+
+    pub fn at(message: &str, span: Span) -> Note {
+        Note {
+            message: message.to_string(),
+            span: Some(span),
+        }
+    }
+}
+
+//every way compiling a program can fail
+#[derive(Debug)]
+pub enum CompileError {
+    //reading the source or writing results
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    //characters the lexer cannot turn into a token
+    Lexical {
+        location: Location,
+        found: String,
+        message: String,
+    },
+    //tokens that do not fit the grammar
+    //  expected holds readable names such as "`;`" or "statement"
+    Syntax {
+        location: Location,
+        expected: Vec<String>,
+        found: String,
+    },
+    //well formed programs that break a language rule
+    Semantic {
+        location: Location,
+        message: String,
+        notes: Vec<Note>,
+    },
+    //generated code that failed while the virtual machine ran it
+    //  instruction is the number shown in the listing
+    Runtime {
+        instruction: usize,
+        message: String,
+    },
+    //a program that failed while the interpreter evaluated its syntax tree
+    //  location is the statement or expression being evaluated
+    Evaluation {
+        location: Location,
+        message: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, CompileError>;
+
+impl CompileError {
+    pub fn io(path: &str, source: io::Error) -> CompileError {
+        CompileError::Io {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            CompileError::Io { .. } | CompileError::Runtime { .. } => None,
+            CompileError::Lexical { location, .. }
+            | CompileError::Syntax { location, .. }
+            | CompileError::Semantic { location, .. }
+            | CompileError::Evaluation { location, .. } => Some(location),
+        }
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        match self {
+            CompileError::Semantic { notes, .. } => notes,
+            _ => &[],
+        }
+    }
+
+    //tag the error with the file it came from
+    pub fn with_file(mut self, file: &str) -> CompileError {
+        match &mut self {
+            CompileError::Io { .. } | CompileError::Runtime { .. } => {}
+            CompileError::Lexical { location, .. }
+            | CompileError::Syntax { location, .. }
+            | CompileError::Semantic { location, .. }
+            | CompileError::Evaluation { location, .. } => location.file = Some(file.to_string()),
+        }
+        self
+    }
+
+    //what kind of failure this is, as shown in front of the message
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CompileError::Io { .. } => "i/o error",
+            CompileError::Lexical { .. } => "lexical error",
+            CompileError::Syntax { .. } => "syntax error",
+            CompileError::Semantic { .. } => "semantic error",
+            CompileError::Runtime { .. } | CompileError::Evaluation { .. } => "runtime error",
+        }
+    }
+
+    //the headline of the error without its location
+    pub fn message(&self) -> String {
+        match self {
+            CompileError::Io { path, source } => format!("{}: {}", path, source),
+            CompileError::Lexical { found, message, .. } => format!("{} `{}`", message, found),
+            CompileError::Syntax {
+                expected, found, ..
+            } => format!("expected {}, found {}", one_of(expected), found),
+            CompileError::Semantic { message, .. } => message.to_string(),
+            CompileError::Runtime {
+                instruction,
+                message,
+            } => format!("{} at instruction {}", message, instruction),
+            CompileError::Evaluation { message, .. } => message.to_string(),
+        }
+    }
+}
+
+//join alternatives as "a, b or c"
+fn one_of(names: &[String]) -> String {
+    match names.split_last() {
+        None => "nothing".to_string(),
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location() {
+            Some(location) => write!(f, "{}: {}: {}", location, self.kind(), self.message()),
+            None => match self {
+                CompileError::Runtime { .. } => write!(f, "{}: {}", self.kind(), self.message()),
+                _ => write!(f, "{}", self.message()),
+            },
+        }
+    }
+}
+
+impl Error for CompileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CompileError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -1,73 +1,65 @@
-#[path = "../lexer/lexer.rs"]
-pub mod lexer;
-use std::env;
+use crate::codegen::Instruction;
+use crate::error::{CompileError, Result};
+use crate::tokens::{TokenKind, TokenType};
 use std::fs::File;
-use std::fs::OpenOptions;
 use std::io;
 use std::io::prelude::*;
-use std::io::BufReader; //for command line arguments, eg filename.txt
+use std::io::BufReader;
 use std::io::Write as IoWrite;
 
-pub fn get_file_name(count: &mut i32) -> String {
-    // Commented because it seems like we'll probably won't be using optional command arguments.
-    //check for command line arguments
-    // let args: Vec<String> = env::args().collect();
-    //if we have command line argument then use that instead of asking the user.print!
-    //  first returned will be the input file, second will be the output file
-
-    // if args.len() > 1 && *count == 0 {
-    //     *count = 1;
-    //     return args[1].to_string();
-    // } else if args.len() > 2 && *count == 1 {
-    //     return args[2].to_string();
-    // }
-
-    //otherwise ask for input
+//ask the user for a path on stdin
+//  only used when the program is started without any command line arguments
+pub fn get_file_name(count: &mut i32) -> Result<String> {
     let mut file_name = String::new();
 
-    if (count as &i32) == &0 {
+    if *count == 0 {
         println!("Please enter the path to the input file: ");
     } else {
         println!("Please enter the path to the output file: ");
     }
 
-    //*count = 1;
-
     io::stdin()
         .read_line(&mut file_name)
-        .expect("Failed to read file name");
+        .map_err(|e| CompileError::io("<stdin>", e))?;
 
-    file_name
+    Ok(file_name)
 }
 
 //read a file to a string and return the string
-pub fn convert_file_to_string(file_name: String) -> String {
+pub fn convert_file_to_string(file_name: String) -> Result<String> {
+    let path = file_name.trim();
     let mut contents = String::new();
 
-    let mut file = BufReader::new(File::open(file_name.trim()).expect("Can't open file!"));
+    let mut file = BufReader::new(File::open(path).map_err(|e| CompileError::io(path, e))?);
 
     file.read_to_string(&mut contents)
-        .expect("Error reading file!");
+        .map_err(|e| CompileError::io(path, e))?;
+
+    Ok(contents)
+}
 
-    contents.push_str(" $");
-    contents
+//write a given vector of tokens to the given output
+pub fn write_tokens(out: &mut dyn IoWrite, token: &[TokenType]) -> io::Result<()> {
+    //iterate over tokens, the end of file token has no text to show
+    for tok in token.iter().filter(|tok| tok.kind != TokenKind::Eof) {
+        let line = format!("{}         {}", tok.kind.class(), tok.kind);
+        writeln!(out, "{:?}", line)?;
+    }
+    Ok(())
 }
 
-//write a given vector of tokens to a given file
-pub fn write_to_file(file_name: String, token: Vec<lexer::TokenType>) {
-    //let mut line: String;
-    let mut file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .append(true)
-        .open(file_name.trim())
-        .unwrap();
+//write the parser's rule trace, one entry per line
+pub fn write_rules(out: &mut dyn IoWrite, rules: &[String]) -> io::Result<()> {
+    for rule in rules.iter() {
+        writeln!(out, "{}", rule)?;
+    }
+    Ok(())
+}
 
-    //iterate over tokens
-    for tok in token.iter() {
-        let line = format!("{}         {}", &tok.lexeme_name, &tok.token);
-        if let Err(e) = writeln!(file, "{:?}", line) {
-            eprintln!("Could not write to file: {}", e);
-        }
+//write a numbered instruction listing, numbers start at 1 to match jump targets
+pub fn write_instructions(out: &mut dyn IoWrite, code: &[Instruction]) -> io::Result<()> {
+    for (index, instruction) in code.iter().enumerate() {
+        writeln!(out, "{:<6}{}", index + 1, instruction)?;
     }
+    Ok(())
 }
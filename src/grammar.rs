@@ -0,0 +1,689 @@
+//grammar of the Rat language written as data
+//  the LL(1) table the parser runs from is built out of PRODUCTIONS, so changing
+//  the language means editing the list below and nothing else
+//
+//  only std is used here because build.rs includes this file too, a grammar
+//  with LL(1) conflicts fails the build before any of the crate is compiled
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::OnceLock;
+use Symbols::*;
+
+//derive operations to perform deep copies of the enum later
+//  names follow the grammar notation rather than rust casing
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Symbols {
+    // Terminals
+    PLUS,    // +
+    MINUS,   // -
+    MULT,    // *
+    DIV,     // /
+    L_PAREN, // (
+    R_PAREN, // )
+    NUM,     // num
+    REAL,    // real
+    ID,      // id
+    EQUAL,
+    INT,
+    BOOL,
+    FLOAT,
+    COMMA,
+    SEMICOLON,
+    IF,
+    THEN,
+    ELSE,
+    ENDIF,
+    WHILE,
+    DO,
+    WHILEEND,
+    FOR,
+    FOREND,
+    DOEND,
+    BEGIN,
+    END,
+    LTHAN,
+    LEQUAL,
+    EQUALTO,
+    NOTEQUAL,
+    GTHANEQUAL,
+    GTHAN,
+    INPUT,
+    OUTPUT,
+    AND,
+    OR,
+    NOT,
+    TRUE,
+    FALSE,
+    ERROR,
+    END_OF_STACK, // $
+
+    // Non-Terminals
+    EXPR,              // Conjunction ExpressionPrime
+    EXPR_PRIME,        // or Conjunction ExpressionPrime | ϵ
+    CONJUNCTION,       // Negation ConjunctionPrime
+    CONJUNCTION_PRIME, // and Negation ConjunctionPrime | ϵ
+    NEGATION,          // not Negation | Relation
+    RELATION,          // Sum RelationPrime
+    RELATION_PRIME,    // Relop Sum | ϵ
+    SUM,               // Term SumPrime
+    SUM_PRIME,         // + Term SumPrime | - Term SumPrime | ϵ
+    TERM,              // Factor TermPrime
+    TERM_PRIME,        // * Factor TermPrime | / Factor TermPrime | ϵ
+    FACTOR,            // - Factor | ( Expression ) | ID | num | real | true | false
+    ID_NT,             // id
+    STATEMENT,         // if Expression then Statement ElsePart endif | PlainStatement
+    PLAIN_STATEMENT,   // every other statement, see PRODUCTIONS
+    ELSE_PART,         // else ElseBody | ϵ
+    ELSE_BODY,         // if Expression then Statement ElsePart | PlainStatement
+    ASSIGN,            // = Expression | ϵ
+    DECLAR,            // Type ID MoreIds
+    TYPE,              // int | bool | float
+    MOREIDS,           // , ID MoreIds | ϵ
+    MOREEXPRS,         // , Expression MoreExprs | ϵ
+    MORESTATEMENTS,    // ; Statement MoreStatements | ϵ
+    RELOP,             // < | <= | == | <> | >= | >
+}
+
+impl Symbols {
+    //terminals are declared before the non-terminals
+    pub fn is_terminal(self) -> bool {
+        self <= END_OF_STACK
+    }
+
+    //readable name for error messages
+    pub fn describe(self) -> &'static str {
+        match self {
+            PLUS => "`+`",
+            MINUS => "`-`",
+            MULT => "`*`",
+            DIV => "`/`",
+            L_PAREN => "`(`",
+            R_PAREN => "`)`",
+            NUM => "number",
+            REAL => "real number",
+            ID => "identifier",
+            EQUAL => "`=`",
+            INT => "`int`",
+            BOOL => "`bool`",
+            FLOAT => "`float`",
+            COMMA => "`,`",
+            SEMICOLON => "`;`",
+            IF => "`if`",
+            THEN => "`then`",
+            ELSE => "`else`",
+            ENDIF => "`endif`",
+            WHILE => "`while`",
+            DO => "`do`",
+            WHILEEND => "`whileend`",
+            FOR => "`for`",
+            FOREND => "`forend`",
+            DOEND => "`doend`",
+            BEGIN => "`begin`",
+            END => "`end`",
+            LTHAN => "`<`",
+            LEQUAL => "`<=`",
+            EQUALTO => "`==`",
+            NOTEQUAL => "`<>`",
+            GTHANEQUAL => "`>=`",
+            GTHAN => "`>`",
+            INPUT => "`input`",
+            OUTPUT => "`output`",
+            AND => "`and`",
+            OR => "`or`",
+            NOT => "`not`",
+            TRUE => "`true`",
+            FALSE => "`false`",
+            ERROR => "invalid token",
+            END_OF_STACK => "end of file",
+            EXPR | EXPR_PRIME | CONJUNCTION | CONJUNCTION_PRIME | NEGATION | RELATION
+            | RELATION_PRIME | SUM | SUM_PRIME | TERM | TERM_PRIME | FACTOR => "expression",
+            ID_NT => "identifier",
+            STATEMENT | PLAIN_STATEMENT | ELSE_BODY | MORESTATEMENTS => "statement",
+            ELSE_PART => "`else` or `endif`",
+            ASSIGN => "assignment",
+            DECLAR => "declaration",
+            TYPE => "type",
+            MOREIDS => "`,` or `;`",
+            MOREEXPRS => "`,` or `)`",
+            RELOP => "comparison operator",
+        }
+    }
+
+    //name used when printing productions in the rule trace
+    pub fn grammar_name(self) -> &'static str {
+        match self {
+            PLUS => "+",
+            MINUS => "-",
+            MULT => "*",
+            DIV => "/",
+            L_PAREN => "(",
+            R_PAREN => ")",
+            NUM => "num",
+            REAL => "real",
+            ID => "id",
+            EQUAL => "=",
+            INT => "int",
+            BOOL => "bool",
+            FLOAT => "float",
+            COMMA => ",",
+            SEMICOLON => ";",
+            IF => "if",
+            THEN => "then",
+            ELSE => "else",
+            ENDIF => "endif",
+            WHILE => "while",
+            DO => "do",
+            WHILEEND => "whileend",
+            FOR => "for",
+            FOREND => "forend",
+            DOEND => "doend",
+            BEGIN => "begin",
+            END => "end",
+            LTHAN => "<",
+            LEQUAL => "<=",
+            EQUALTO => "==",
+            NOTEQUAL => "<>",
+            GTHANEQUAL => ">=",
+            GTHAN => ">",
+            INPUT => "input",
+            OUTPUT => "output",
+            AND => "and",
+            OR => "or",
+            NOT => "not",
+            TRUE => "true",
+            FALSE => "false",
+            ERROR => "error",
+            END_OF_STACK => "$",
+            EXPR => "Expression",
+            EXPR_PRIME => "ExpressionPrime",
+            CONJUNCTION => "Conjunction",
+            CONJUNCTION_PRIME => "ConjunctionPrime",
+            NEGATION => "Negation",
+            RELATION => "Relation",
+            RELATION_PRIME => "RelationPrime",
+            SUM => "Sum",
+            SUM_PRIME => "SumPrime",
+            TERM => "Term",
+            TERM_PRIME => "TermPrime",
+            FACTOR => "Factor",
+            ID_NT => "ID",
+            STATEMENT => "Statement",
+            PLAIN_STATEMENT => "PlainStatement",
+            ELSE_PART => "ElsePart",
+            ELSE_BODY => "ElseBody",
+            ASSIGN => "Assign",
+            DECLAR => "Declarative",
+            TYPE => "Type",
+            MOREIDS => "MoreIds",
+            MOREEXPRS => "MoreExprs",
+            MORESTATEMENTS => "MoreStatements",
+            RELOP => "Relop",
+        }
+    }
+}
+
+//one rule of the grammar, an empty right hand side is ϵ
+#[derive(Debug, PartialEq, Eq)]
+pub struct Production {
+    pub lhs: Symbols,
+    pub rhs: &'static [Symbols],
+}
+
+const fn rule(lhs: Symbols, rhs: &'static [Symbols]) -> Production {
+    Production { lhs, rhs }
+}
+
+impl fmt::Display for Production {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ⟶", self.lhs.grammar_name())?;
+        if self.rhs.is_empty() {
+            return write!(f, " ϵ");
+        }
+        for symbol in self.rhs {
+            write!(f, " {}", symbol.grammar_name())?;
+        }
+        Ok(())
+    }
+}
+
+//a program is a single statement, normally a begin ... end block
+pub const START: Symbols = STATEMENT;
+
+//  an assignment and an expression statement both start with an expression,
+//  `PlainStatement -> Expression Assign` parses the left side first and the syntax
+//  tree checks that only an identifier is assigned to
+//  `else if` continues the same if instead of nesting a new one, so a chain
+//  closes with a single endif, ElseBody repeats the if alternative of Statement
+//  and takes every other statement from PlainStatement to stay LL(1)
+//  operators bind from loosest to tightest: or, and, not, relops, + -, * /
+pub const PRODUCTIONS: &[Production] = &[
+    rule(STATEMENT, &[IF, EXPR, THEN, STATEMENT, ELSE_PART, ENDIF]),
+    rule(STATEMENT, &[PLAIN_STATEMENT]),
+    rule(ELSE_PART, &[ELSE, ELSE_BODY]),
+    rule(ELSE_PART, &[]),
+    rule(ELSE_BODY, &[IF, EXPR, THEN, STATEMENT, ELSE_PART]),
+    rule(ELSE_BODY, &[PLAIN_STATEMENT]),
+    rule(PLAIN_STATEMENT, &[DECLAR]),
+    rule(PLAIN_STATEMENT, &[EXPR, ASSIGN]),
+    rule(
+        PLAIN_STATEMENT,
+        &[WHILE, EXPR, DO, STATEMENT, MORESTATEMENTS, WHILEEND],
+    ),
+    rule(
+        PLAIN_STATEMENT,
+        &[
+            FOR,
+            ID_NT,
+            EQUAL,
+            EXPR,
+            COMMA,
+            EXPR,
+            DO,
+            STATEMENT,
+            MORESTATEMENTS,
+            FOREND,
+        ],
+    ),
+    rule(
+        PLAIN_STATEMENT,
+        &[DO, STATEMENT, MORESTATEMENTS, DOEND, WHILE, EXPR],
+    ),
+    rule(PLAIN_STATEMENT, &[BEGIN, STATEMENT, MORESTATEMENTS, END]),
+    rule(PLAIN_STATEMENT, &[INPUT, L_PAREN, ID_NT, MOREIDS, R_PAREN]),
+    rule(
+        PLAIN_STATEMENT,
+        &[OUTPUT, L_PAREN, EXPR, MOREEXPRS, R_PAREN],
+    ),
+    rule(PLAIN_STATEMENT, &[]),
+    rule(MORESTATEMENTS, &[SEMICOLON, STATEMENT, MORESTATEMENTS]),
+    rule(MORESTATEMENTS, &[]),
+    rule(ASSIGN, &[EQUAL, EXPR]),
+    rule(ASSIGN, &[]),
+    rule(DECLAR, &[TYPE, ID_NT, MOREIDS]),
+    rule(TYPE, &[INT]),
+    rule(TYPE, &[BOOL]),
+    rule(TYPE, &[FLOAT]),
+    rule(MOREIDS, &[COMMA, ID_NT, MOREIDS]),
+    rule(MOREIDS, &[]),
+    rule(MOREEXPRS, &[COMMA, EXPR, MOREEXPRS]),
+    rule(MOREEXPRS, &[]),
+    rule(RELOP, &[LTHAN]),
+    rule(RELOP, &[LEQUAL]),
+    rule(RELOP, &[EQUALTO]),
+    rule(RELOP, &[NOTEQUAL]),
+    rule(RELOP, &[GTHANEQUAL]),
+    rule(RELOP, &[GTHAN]),
+    rule(EXPR, &[CONJUNCTION, EXPR_PRIME]),
+    rule(EXPR_PRIME, &[OR, CONJUNCTION, EXPR_PRIME]),
+    rule(EXPR_PRIME, &[]),
+    rule(CONJUNCTION, &[NEGATION, CONJUNCTION_PRIME]),
+    rule(CONJUNCTION_PRIME, &[AND, NEGATION, CONJUNCTION_PRIME]),
+    rule(CONJUNCTION_PRIME, &[]),
+    rule(NEGATION, &[NOT, NEGATION]),
+    rule(NEGATION, &[RELATION]),
+    rule(RELATION, &[SUM, RELATION_PRIME]),
+    rule(RELATION_PRIME, &[RELOP, SUM]),
+    rule(RELATION_PRIME, &[]),
+    rule(SUM, &[TERM, SUM_PRIME]),
+    rule(SUM_PRIME, &[PLUS, TERM, SUM_PRIME]),
+    rule(SUM_PRIME, &[MINUS, TERM, SUM_PRIME]),
+    rule(SUM_PRIME, &[]),
+    rule(TERM, &[FACTOR, TERM_PRIME]),
+    rule(TERM_PRIME, &[MULT, FACTOR, TERM_PRIME]),
+    rule(TERM_PRIME, &[DIV, FACTOR, TERM_PRIME]),
+    rule(TERM_PRIME, &[]),
+    rule(FACTOR, &[MINUS, FACTOR]),
+    rule(FACTOR, &[L_PAREN, EXPR, R_PAREN]),
+    rule(FACTOR, &[ID_NT]),
+    rule(FACTOR, &[NUM]),
+    rule(FACTOR, &[REAL]),
+    rule(FACTOR, &[TRUE]),
+    rule(FACTOR, &[FALSE]),
+    rule(ID_NT, &[ID]),
+];
+
+//a grammar with its FIRST and FOLLOW sets worked out
+pub struct Grammar {
+    pub productions: &'static [Production],
+    pub start: Symbols,
+    first: HashMap<Symbols, BTreeSet<Symbols>>,
+    nullable: BTreeSet<Symbols>, // non-terminals that can derive ϵ
+    follow: HashMap<Symbols, BTreeSet<Symbols>>,
+}
+
+impl Grammar {
+    pub fn new(productions: &'static [Production], start: Symbols) -> Grammar {
+        let mut grammar = Grammar {
+            productions,
+            start,
+            first: HashMap::new(),
+            nullable: BTreeSet::new(),
+            follow: HashMap::new(),
+        };
+        grammar.compute_first();
+        grammar.compute_follow();
+        grammar
+    }
+
+    //terminals that can start the sequence, and whether all of it can vanish
+    pub fn first_of(&self, symbols: &[Symbols]) -> (BTreeSet<Symbols>, bool) {
+        let mut first = BTreeSet::new();
+        for symbol in symbols {
+            if symbol.is_terminal() {
+                first.insert(*symbol);
+                return (first, false);
+            }
+            if let Some(set) = self.first.get(symbol) {
+                first.extend(set.iter().copied());
+            }
+            if !self.nullable.contains(symbol) {
+                return (first, false);
+            }
+        }
+        (first, true)
+    }
+
+    pub fn first(&self, symbol: Symbols) -> BTreeSet<Symbols> {
+        self.first_of(&[symbol]).0
+    }
+
+    pub fn follow(&self, symbol: Symbols) -> BTreeSet<Symbols> {
+        self.follow.get(&symbol).cloned().unwrap_or_default()
+    }
+
+    pub fn nullable(&self, symbol: Symbols) -> bool {
+        self.nullable.contains(&symbol)
+    }
+
+    //grow the FIRST sets until no production adds anything new
+    fn compute_first(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in self.productions {
+                let (first, nullable) = self.first_of(production.rhs);
+                let entry = self.first.entry(production.lhs).or_default();
+                let before = entry.len();
+                entry.extend(first);
+                changed |= entry.len() != before;
+                if nullable {
+                    changed |= self.nullable.insert(production.lhs);
+                }
+            }
+        }
+    }
+
+    //grow the FOLLOW sets the same way, the start symbol is followed by $
+    fn compute_follow(&mut self) {
+        self.follow
+            .entry(self.start)
+            .or_default()
+            .insert(END_OF_STACK);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in self.productions {
+                for (index, symbol) in production.rhs.iter().enumerate() {
+                    if symbol.is_terminal() {
+                        continue;
+                    }
+                    let (mut follow, nullable) = self.first_of(&production.rhs[index + 1..]);
+                    if nullable {
+                        follow.extend(self.follow(production.lhs));
+                    }
+                    let entry = self.follow.entry(*symbol).or_default();
+                    let before = entry.len();
+                    entry.extend(follow);
+                    changed |= entry.len() != before;
+                }
+            }
+        }
+    }
+
+    //fill one cell per non-terminal and lookahead terminal
+    //  a production goes under every terminal in FIRST of its right hand side,
+    //  and under FOLLOW of its left hand side when the right hand side can vanish
+    //  two productions landing in the same cell is an LL(1) conflict
+    pub fn table(&self) -> Result<ParseTable, Vec<Conflict>> {
+        let mut cells: HashMap<(Symbols, Symbols), &'static Production> = HashMap::new();
+        let mut conflicts = Vec::new();
+
+        for production in self.productions {
+            let (mut lookahead, nullable) = self.first_of(production.rhs);
+            if nullable {
+                lookahead.extend(self.follow(production.lhs));
+            }
+            for terminal in lookahead {
+                match cells.get(&(production.lhs, terminal)) {
+                    Some(existing) => conflicts.push(Conflict {
+                        nonterminal: production.lhs,
+                        terminal,
+                        first: existing,
+                        second: production,
+                    }),
+                    None => {
+                        cells.insert((production.lhs, terminal), production);
+                    }
+                }
+            }
+        }
+
+        if conflicts.is_empty() {
+            Ok(ParseTable {
+                cells,
+                first: self.first.clone(),
+                nullable: self.nullable.clone(),
+            })
+        } else {
+            Err(conflicts)
+        }
+    }
+}
+
+//the grammar of the Rat language
+pub fn rat() -> Grammar {
+    Grammar::new(PRODUCTIONS, START)
+}
+
+//LL(1) table for the Rat grammar, built the first time the parser asks for it
+//  build.rs has already refused to build a grammar with conflicts
+pub fn table() -> &'static ParseTable {
+    static TABLE: OnceLock<ParseTable> = OnceLock::new();
+    TABLE.get_or_init(|| match rat().table() {
+        Ok(table) => table,
+        Err(conflicts) => panic!("grammar is not LL(1): {}", conflicts[0]),
+    })
+}
+
+//predictive parse table
+pub struct ParseTable {
+    cells: HashMap<(Symbols, Symbols), &'static Production>,
+    first: HashMap<Symbols, BTreeSet<Symbols>>,
+    nullable: BTreeSet<Symbols>,
+}
+
+impl ParseTable {
+    //production to expand the non-terminal with when the terminal is next
+    pub fn get(&self, nonterminal: Symbols, terminal: Symbols) -> Option<&'static Production> {
+        self.cells.get(&(nonterminal, terminal)).copied()
+    }
+
+    //terminals that can come next with this stack, top last, in declaration order
+    //  FIRST of the top symbol, and of each one below it while the symbols above
+    //  can vanish, rather than the table row, which holds all of FOLLOW for a
+    //  nullable non-terminal whatever is actually waiting beneath it
+    pub fn expected(&self, stack: &[Symbols]) -> Vec<Symbols> {
+        let mut terminals = BTreeSet::new();
+        for symbol in stack.iter().rev() {
+            if symbol.is_terminal() {
+                terminals.insert(*symbol);
+                break;
+            }
+            if let Some(first) = self.first.get(symbol) {
+                terminals.extend(first.iter().copied());
+            }
+            if !self.nullable.contains(symbol) {
+                break;
+            }
+        }
+        terminals.into_iter().collect()
+    }
+}
+
+//two productions competing for the same table cell
+#[derive(Debug)]
+pub struct Conflict {
+    pub nonterminal: Symbols,
+    pub terminal: Symbols,
+    pub first: &'static Production,
+    pub second: &'static Production,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "LL(1) conflict for {} on `{}`: `{}` and `{}`",
+            self.nonterminal.grammar_name(),
+            self.terminal.grammar_name(),
+            self.first,
+            self.second
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //the textbook expression grammar, E -> T E', E' -> + T E' | ϵ, T -> F T',
+    //  T' -> * F T' | ϵ, F -> ( E ) | id
+    const EXPRESSIONS: &[Production] = &[
+        rule(SUM, &[TERM, SUM_PRIME]),
+        rule(SUM_PRIME, &[PLUS, TERM, SUM_PRIME]),
+        rule(SUM_PRIME, &[]),
+        rule(TERM, &[FACTOR, TERM_PRIME]),
+        rule(TERM_PRIME, &[MULT, FACTOR, TERM_PRIME]),
+        rule(TERM_PRIME, &[]),
+        rule(FACTOR, &[L_PAREN, SUM, R_PAREN]),
+        rule(FACTOR, &[ID]),
+    ];
+
+    //dangling else, `if e then if e then s else s` has two parses
+    const DANGLING_ELSE: &[Production] = &[
+        rule(STATEMENT, &[IF, EXPR, THEN, STATEMENT, ELSE_PART]),
+        rule(STATEMENT, &[ID]),
+        rule(ELSE_PART, &[ELSE, STATEMENT]),
+        rule(ELSE_PART, &[]),
+        rule(EXPR, &[ID]),
+    ];
+
+    //two alternatives starting with the same terminal
+    const COMMON_PREFIX: &[Production] =
+        &[rule(FACTOR, &[ID]), rule(FACTOR, &[ID, L_PAREN, R_PAREN])];
+
+    fn set(symbols: &[Symbols]) -> BTreeSet<Symbols> {
+        symbols.iter().copied().collect()
+    }
+
+    #[test]
+    fn first_sets() {
+        let grammar = Grammar::new(EXPRESSIONS, SUM);
+        assert_eq!(grammar.first(SUM), set(&[L_PAREN, ID]));
+        assert_eq!(grammar.first(TERM), set(&[L_PAREN, ID]));
+        assert_eq!(grammar.first(FACTOR), set(&[L_PAREN, ID]));
+        assert_eq!(grammar.first(SUM_PRIME), set(&[PLUS]));
+        assert_eq!(grammar.first(TERM_PRIME), set(&[MULT]));
+        assert!(grammar.nullable(SUM_PRIME));
+        assert!(grammar.nullable(TERM_PRIME));
+        assert!(!grammar.nullable(SUM));
+
+        //a nullable prefix lets the next symbol's FIRST through
+        assert_eq!(
+            grammar.first_of(&[SUM_PRIME, TERM_PRIME]),
+            (set(&[PLUS, MULT]), true)
+        );
+        assert_eq!(
+            grammar.first_of(&[TERM_PRIME, FACTOR]),
+            (set(&[MULT, L_PAREN, ID]), false)
+        );
+    }
+
+    #[test]
+    fn follow_sets() {
+        let grammar = Grammar::new(EXPRESSIONS, SUM);
+        assert_eq!(grammar.follow(SUM), set(&[R_PAREN, END_OF_STACK]));
+        assert_eq!(grammar.follow(SUM_PRIME), set(&[R_PAREN, END_OF_STACK]));
+        assert_eq!(grammar.follow(TERM), set(&[PLUS, R_PAREN, END_OF_STACK]));
+        assert_eq!(
+            grammar.follow(TERM_PRIME),
+            set(&[PLUS, R_PAREN, END_OF_STACK])
+        );
+        assert_eq!(
+            grammar.follow(FACTOR),
+            set(&[PLUS, MULT, R_PAREN, END_OF_STACK])
+        );
+    }
+
+    #[test]
+    fn table_cells() {
+        let table = match Grammar::new(EXPRESSIONS, SUM).table() {
+            Ok(table) => table,
+            Err(conflicts) => panic!("unexpected conflict: {}", conflicts[0]),
+        };
+        assert_eq!(table.get(SUM, ID), Some(&EXPRESSIONS[0]));
+        assert_eq!(table.get(SUM_PRIME, PLUS), Some(&EXPRESSIONS[1]));
+        //the ϵ production goes under FOLLOW
+        assert_eq!(table.get(SUM_PRIME, R_PAREN), Some(&EXPRESSIONS[2]));
+        assert_eq!(table.get(SUM_PRIME, END_OF_STACK), Some(&EXPRESSIONS[2]));
+        assert_eq!(table.get(SUM_PRIME, MULT), None);
+        assert_eq!(table.expected(&[FACTOR]), vec![L_PAREN, ID]);
+        //a nullable top lets the symbols beneath it through, and only them
+        assert_eq!(
+            table.expected(&[END_OF_STACK, R_PAREN, SUM_PRIME, TERM_PRIME]),
+            vec![PLUS, MULT, R_PAREN]
+        );
+        assert_eq!(table.expected(&[SUM_PRIME, FACTOR]), vec![L_PAREN, ID]);
+    }
+
+    #[test]
+    fn first_follow_conflict() {
+        let conflicts = match Grammar::new(DANGLING_ELSE, STATEMENT).table() {
+            Ok(_) => panic!("dangling else should not be LL(1)"),
+            Err(conflicts) => conflicts,
+        };
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].nonterminal, ELSE_PART);
+        assert_eq!(conflicts[0].terminal, ELSE);
+        assert_eq!(conflicts[0].first, &DANGLING_ELSE[2]);
+        assert_eq!(conflicts[0].second, &DANGLING_ELSE[3]);
+    }
+
+    #[test]
+    fn first_first_conflict() {
+        let conflicts = match Grammar::new(COMMON_PREFIX, FACTOR).table() {
+            Ok(_) => panic!("a shared first terminal should not be LL(1)"),
+            Err(conflicts) => conflicts,
+        };
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].nonterminal, FACTOR);
+        assert_eq!(conflicts[0].terminal, ID);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "LL(1) conflict for Factor on `id`: `Factor ⟶ id` and `Factor ⟶ id ( )`"
+        );
+    }
+
+    #[test]
+    fn rat_grammar_is_ll1() {
+        let grammar = rat();
+        assert!(grammar.table().is_ok());
+        assert!(grammar.nullable(STATEMENT));
+        assert!(grammar
+            .first(STATEMENT)
+            .is_superset(&set(&[IF, BEGIN, WHILE, INT, ID])));
+        assert!(grammar.follow(ELSE_PART).contains(&ENDIF));
+    }
+}
@@ -0,0 +1,270 @@
+//tree walking interpreter, runs a checked program without generating code
+//  every declared variable gets a cell, indexed like the memory the symbol table
+//  hands out, that holds no value until something is stored in it
+//  values and arithmetic are the virtual machine's, so both print the same output
+use crate::ast::{
+    BinaryOp, Expr, ExprKind, For, Ident, Input, Literal, LogicalOp, Output, Program, Relop,
+    Statement, Type, UnaryOp,
+};
+use crate::error::{CompileError, Location, Result};
+use crate::symbol_table::SymbolTable;
+use crate::tokens::Span;
+use crate::vm::{Value, Words};
+use std::io::{BufRead, Write as IoWrite};
+
+//run a program to the end, finding variables through SymbolTable::resolve
+//  input reads whitespace separated numbers, output prints one value per line
+pub fn run(
+    program: &Program,
+    symbols: &SymbolTable,
+    input: &mut dyn BufRead,
+    output: &mut dyn IoWrite,
+) -> Result<()> {
+    let mut interpreter = Interpreter {
+        symbols,
+        cells: vec![None; symbols.len()],
+        words: Words::default(),
+        input,
+        output,
+    };
+    for statement in program.statements.iter() {
+        interpreter.statement(statement)?;
+    }
+    interpreter
+        .output
+        .flush()
+        .map_err(|e| CompileError::io("<output>", e))
+}
+
+struct Interpreter<'a> {
+    symbols: &'a SymbolTable,
+    cells: Vec<Option<Value>>, // None until the variable is given a value
+    words: Words,
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn IoWrite,
+}
+
+impl<'a> Interpreter<'a> {
+    //loops and input and output run in their own methods, which keeps this frame
+    //  small since nested statements come back through here once per level
+    fn statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            //a declaration met again, in a loop body, starts the variable over
+            Statement::Declaration(declaration) => {
+                for name in declaration.names.iter() {
+                    let cell = self.cell(name)?;
+                    self.cells[cell] = None;
+                }
+            }
+
+            Statement::Assign(assign) => {
+                let value = self.expr(&assign.value)?;
+                self.store(&assign.target, value)?;
+            }
+
+            Statement::If(i) => {
+                if self.holds(&i.condition)? {
+                    self.statement(&i.then_branch)?;
+                } else if let Some(else_branch) = &i.else_branch {
+                    self.statement(else_branch)?;
+                }
+            }
+
+            Statement::While(w) => {
+                while self.holds(&w.condition)? {
+                    self.statement(&w.body)?;
+                }
+            }
+
+            Statement::For(f) => self.for_loop(f)?,
+
+            Statement::DoWhile(d) => loop {
+                self.statement(&d.body)?;
+                if !self.holds(&d.condition)? {
+                    break;
+                }
+            },
+
+            Statement::Block(b) => {
+                for statement in b.statements.iter() {
+                    self.statement(statement)?;
+                }
+            }
+
+            Statement::Input(input) => self.read_input(input)?,
+
+            Statement::Output(output) => self.write_output(output)?,
+
+            //the code generator emits nothing for a bare expression, so neither
+            //  does this evaluate one
+            Statement::Expr(_) => {}
+        }
+        Ok(())
+    }
+
+    //end is evaluated inside the loop, see ast::For
+    fn for_loop(&mut self, f: &For) -> Result<()> {
+        let start = self.expr(&f.start)?;
+        self.store(&f.variable, start)?;
+        loop {
+            let counter = self.load(&f.variable)?;
+            let end = self.expr(&f.end)?;
+            if !counter.compare(end, |a, b| a <= b, |a, b| a <= b) {
+                return Ok(());
+            }
+            self.statement(&f.body)?;
+            let counter = self.load(&f.variable)?;
+            let next = counter.arithmetic(Value::Int(1), i64::checked_add, |a, b| a + b);
+            match next {
+                Some(next) => self.store(&f.variable, next)?,
+                None => return Err(error(f.variable.span, "integer overflow")),
+            }
+        }
+    }
+
+    fn read_input(&mut self, input: &Input) -> Result<()> {
+        for target in input.targets.iter() {
+            let word = match self.words.next(self.input)? {
+                Some(word) => word,
+                None => {
+                    return Err(error(
+                        target.span,
+                        &format!("input ended before `{}` could be read", target.name),
+                    ))
+                }
+            };
+            let value = Value::parse(&word).ok_or_else(|| {
+                error(
+                    target.span,
+                    &format!("`input` expected a number, found `{}`", word),
+                )
+            })?;
+            self.store(target, value)?;
+        }
+        Ok(())
+    }
+
+    fn write_output(&mut self, output: &Output) -> Result<()> {
+        for value in output.values.iter() {
+            let value = self.expr(value)?;
+            writeln!(self.output, "{}", value).map_err(|e| CompileError::io("<output>", e))?;
+        }
+        Ok(())
+    }
+
+    //value of a bool expression
+    fn holds(&mut self, condition: &Expr) -> Result<bool> {
+        Ok(!self.expr(condition)?.is_zero())
+    }
+
+    //true is 1 and false is 0, the same values the machine uses
+    fn expr(&mut self, expr: &Expr) -> Result<Value> {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Int(value)) => Ok(Value::Int(*value)),
+            ExprKind::Literal(Literal::Float(value)) => Ok(Value::Float(*value)),
+            ExprKind::Literal(Literal::Bool(value)) => Ok(Value::Int(*value as i64)),
+            ExprKind::Var(ident) => self.load(ident),
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                let value = match op {
+                    BinaryOp::Add => lhs.arithmetic(rhs, i64::checked_add, |a, b| a + b),
+                    BinaryOp::Sub => lhs.arithmetic(rhs, i64::checked_sub, |a, b| a - b),
+                    BinaryOp::Mul => lhs.arithmetic(rhs, i64::checked_mul, |a, b| a * b),
+                    BinaryOp::Div => {
+                        if rhs.is_zero() {
+                            return Err(error(expr.span, "division by zero"));
+                        }
+                        lhs.arithmetic(rhs, i64::checked_div, |a, b| a / b)
+                    }
+                };
+                value.ok_or_else(|| error(expr.span, "integer overflow"))
+            }
+            ExprKind::Relop { op, lhs, rhs } => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                let holds = match op {
+                    Relop::Less => lhs.compare(rhs, |a, b| a < b, |a, b| a < b),
+                    Relop::LessEqual => lhs.compare(rhs, |a, b| a <= b, |a, b| a <= b),
+                    Relop::Equal => lhs.compare(rhs, |a, b| a == b, |a, b| a == b),
+                    Relop::NotEqual => lhs.compare(rhs, |a, b| a != b, |a, b| a != b),
+                    Relop::GreaterEqual => lhs.compare(rhs, |a, b| a >= b, |a, b| a >= b),
+                    Relop::Greater => lhs.compare(rhs, |a, b| a > b, |a, b| a > b),
+                };
+                Ok(Value::Int(holds as i64))
+            }
+            //short circuit, the right side only runs when it decides the result
+            ExprKind::Logical { op, lhs, rhs } => {
+                let lhs = self.holds(lhs)?;
+                let holds = match op {
+                    LogicalOp::And => lhs && self.holds(rhs)?,
+                    LogicalOp::Or => lhs || self.holds(rhs)?,
+                };
+                Ok(Value::Int(holds as i64))
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => Ok(Value::Int(self.expr(operand)?.is_zero() as i64)),
+            ExprKind::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => {
+                let value = self.expr(operand)?;
+                Value::Int(0)
+                    .arithmetic(value, i64::checked_sub, |a, b| a - b)
+                    .ok_or_else(|| error(expr.span, "integer overflow"))
+            }
+        }
+    }
+
+    fn load(&self, name: &Ident) -> Result<Value> {
+        let cell = self.cell(name)?;
+        self.cells[cell].ok_or_else(|| {
+            error(
+                name.span,
+                &format!("`{}` is read before it is given a value", name.name),
+            )
+        })
+    }
+
+    //ints stored in a float variable become floats, only input can offer a float
+    //  to an int variable
+    fn store(&mut self, name: &Ident, value: Value) -> Result<()> {
+        let cell = self.cell(name)?;
+        let ty = self.symbols.symbols()[cell].ty;
+        let value = match (ty, value) {
+            (Type::Float, Value::Int(value)) => Value::Float(value as f64),
+            (Type::Int, Value::Float(value)) => {
+                return Err(error(
+                    name.span,
+                    &format!(
+                        "cannot store the real number {:?} in the int variable `{}`",
+                        value, name.name
+                    ),
+                ))
+            }
+            _ => value,
+        };
+        self.cells[cell] = Some(value);
+        Ok(())
+    }
+
+    //index of the cell a name refers to
+    fn cell(&self, name: &Ident) -> Result<usize> {
+        match self.symbols.resolve(name) {
+            Some(symbol) => Ok(symbol.address - self.symbols.base_address()),
+            None => Err(error(
+                name.span,
+                &format!("no variable for `{}`", name.name),
+            )),
+        }
+    }
+}
+
+fn error(span: Span, message: &str) -> CompileError {
+    CompileError::Evaluation {
+        location: Location::from(span),
+        message: message.to_string(),
+    }
+}
@@ -1,6 +1,7 @@
 pub use FsmTransitions::*;
 
 //derive operations to perform deep copies of the enum later
+#[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum FsmTransitions {
     _Reject,
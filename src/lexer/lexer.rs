@@ -1,94 +1,335 @@
-#[path = "../tokens/tokens.rs"]
-pub mod tokens;
-pub use tokens::*;
+use crate::error::{CompileError, Location, Result};
+use crate::fsm;
+use crate::fsm::FsmTransitions;
+use crate::fsm::FsmTransitions::*;
+use crate::tokens::{Position, Span, TokenKind, TokenType};
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, BufRead};
+use std::rc::Rc;
 
 //parse a given string into a vector of tokens via use of FSM
 // to modify change "fsm.rs"
-pub fn lexer(expression: &String) -> Vec<TokenType> {
-    let mut access = TokenType::default();
-    let mut tokens: Vec<TokenType> = Vec::new(); //stores tokens previously collected
-    let mut col: FsmTransitions; //current column of Table
-    let mut prev_state: FsmTransitions = _Reject; //Previous state we were in
-    let mut current_state: FsmTransitions = _Reject; //Current state we are in
-    let mut current_token = String::new(); //current token we are working on
-    let mut index = 0;
-    let mut line_count = 1;
-    //loop through characters
-    while index != expression.len() {
-        col = get_col(match expression.chars().nth(index) {
-            Some(c) => c,
-            None => ' ',
-        });
+//  fails on the first character sequence the FSM cannot accept
+pub fn lexer(expression: &str) -> Result<Vec<TokenType>> {
+    Tokens::new(expression.as_bytes()).collect()
+}
+
+//tokens read lazily from any buffered source, such as a file, stdin or a pipe
+//  yields exactly what lexer() returns, ending with the EOF token, and stops
+//  after the first error
+//  holds at most CHUNK_SIZE bytes of input, the token being built and one copy of
+//  each distinct identifier name, never the whole source
+pub struct Tokens<R: BufRead> {
+    input: R,
+    chunk: String,              //piece of input being scanned
+    at: usize,                  //byte index of the next character in chunk
+    split: Vec<u8>,             //start of a character cut off at the end of the last read
+    text: String,               //text of the token being built
+    prev_state: FsmTransitions, //Previous state we were in
+    position: Position,         //where the next character sits
+    token_start: Position,      //where the current token began
+    ready: VecDeque<TokenType>, //finished tokens not handed out yet
+    names: HashSet<Rc<str>>,    //identifier names seen so far
+    done: bool,                 //the EOF token or an error has been produced
+    file: String,               //what read errors call the input
+}
+
+impl<R: BufRead> Tokens<R> {
+    pub fn new(input: R) -> Tokens<R> {
+        Tokens {
+            input,
+            chunk: String::new(),
+            at: 0,
+            split: Vec::new(),
+            text: String::new(),
+            prev_state: _Reject,
+            position: Position::default(),
+            token_start: Position::default(),
+            ready: VecDeque::new(),
+            names: HashSet::new(),
+            done: false,
+            file: "<input>".to_string(),
+        }
+    }
+
+    //name the input in read errors, such as the path of the file being read
+    pub fn with_file(mut self, file: &str) -> Tokens<R> {
+        self.file = file.to_string();
+        self
+    }
+
+    //the next character, reading another chunk once this one is used up
+    fn peek(&mut self) -> Result<Option<char>> {
+        if self.at >= self.chunk.len() {
+            self.read_chunk()
+                .map_err(|e| CompileError::io(&self.file, e))?;
+        }
+        Ok(self.chunk[self.at..].chars().next())
+    }
 
-        //Debug code here
-        // println!("Collum is {} .", col as i32);
-        current_state = fsm::STATE_TABLE[current_state as usize][col as usize];
+    //replace the chunk with the next CHUNK_SIZE bytes of input, leaving it empty at
+    //  the end of the input
+    //  a character split between two reads is held back until the rest arrives
+    fn read_chunk(&mut self) -> io::Result<()> {
+        self.chunk.clear();
+        self.at = 0;
+        while self.chunk.is_empty() {
+            let bytes = match self.input.fill_buf() {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if bytes.is_empty() {
+                if !self.split.is_empty() {
+                    return Err(invalid_utf8());
+                }
+                return Ok(());
+            }
+            let length = bytes.len().min(CHUNK_SIZE);
+            self.split.extend_from_slice(&bytes[..length]);
+            self.input.consume(length);
+
+            let valid = match std::str::from_utf8(&self.split) {
+                Ok(_) => self.split.len(),
+                //error_len is None when the bytes so far are only cut short
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => return Err(invalid_utf8()),
+            };
+            let text = std::str::from_utf8(&self.split[..valid]).map_err(|_| invalid_utf8())?;
+            self.chunk.push_str(text);
+            self.split.drain(..valid);
+        }
+        Ok(())
+    }
+
+    //run the FSM over one character, or finish at the end of the input
+    //  a rejected character is looked at again from the starting state, so it is
+    //  only consumed once some state accepts it
+    fn step(&mut self) -> Result<()> {
+        let c = match self.peek()? {
+            Some(c) => c,
+            None => return self.finish(),
+        };
+        let col = get_col(c);
+        let current_state = fsm::STATE_TABLE[self.prev_state as usize][col as usize];
 
         //for rejection state
         if current_state == _Reject {
-            if prev_state == _Comment {
-                line_count = line_count + 1;
-                index = index + 1;
-            }
-            if prev_state == _New_line {
-                line_count = line_count + 1;
-                //index = index + 1;
+            //the closing '!' of a comment is not part of the next token
+            if self.prev_state == _Comment {
+                self.consume(c);
             }
 
             //check precursor states for validity, if not found then continue to next
+            let prev_state = self.prev_state;
             if prev_state != _Space && prev_state != _New_line && prev_state != _Comment {
-                if prev_state == _Comment {
-                    current_token.push(match expression.chars().nth(index) {
-                        Some(c) => c,
-                        None => ' ',
-                    });
-
-                    index = index + 1;
-                }
-
-                //check if string is identifier or keyword
-
-                if prev_state == _String {
-                    prev_state = get_string_type(current_token.clone());
+                let span = Span::new(self.token_start, self.position);
+                if prev_state == _Unknown {
+                    return Err(unknown_token(&self.text, span));
                 }
 
                 //add the valid token
-                access.token = current_token;
-                access.lexeme = prev_state;
-                access.lexeme_name = get_lexeme_name(&access.lexeme);
-                access.line = line_count;
-                tokens.push(access.clone());
+                push(
+                    &mut self.ready,
+                    &mut self.names,
+                    &self.text,
+                    prev_state,
+                    span,
+                )?;
             }
-
-            current_token = "".to_string();
+            self.text.clear();
         } else {
-            current_token.push(match expression.chars().nth(index) {
-                Some(c) => c,
-                None => ' ',
+            if self.prev_state == _Reject {
+                self.token_start = self.position;
+            }
+            //spaces and comments are dropped, so their text is never kept
+            if current_state != _Space && current_state != _New_line && current_state != _Comment {
+                self.text.push(c);
+            }
+            self.consume(c);
+        }
+
+        self.prev_state = current_state;
+        Ok(())
+    }
+
+    fn consume(&mut self, c: char) {
+        self.at += c.len_utf8();
+        self.position.advance(c);
+    }
+
+    //whatever token the input ends in, then the EOF token
+    fn finish(&mut self) -> Result<()> {
+        self.done = true;
+        let state = self.prev_state;
+        let span = Span::new(self.token_start, self.position);
+        if state == _Comment {
+            return Err(CompileError::Lexical {
+                location: Location::from(Span::new(self.token_start, self.token_start)),
+                found: "!".to_string(),
+                message: "comment is never closed, expected a matching".to_string(),
             });
+        }
+        if state == _Unknown {
+            return Err(unknown_token(&self.text, span));
+        }
+        if state != _Reject && state != _Space && state != _New_line {
+            push(&mut self.ready, &mut self.names, &self.text, state, span)?;
+        }
+
+        //the parser stops on this, it covers no text
+        self.ready.push_back(TokenType {
+            kind: TokenKind::Eof,
+            span: Span::new(self.position, self.position),
+        });
+        Ok(())
+    }
+}
 
-            index = index + 1;
+impl<R: BufRead> Iterator for Tokens<R> {
+    type Item = Result<TokenType>;
+
+    fn next(&mut self) -> Option<Result<TokenType>> {
+        while self.ready.is_empty() && !self.done {
+            if let Err(e) = self.step() {
+                self.done = true;
+                return Some(Err(e));
+            }
         }
+        self.ready.pop_front().map(Ok)
+    }
+}
+
+//most input bytes read at once
+const CHUNK_SIZE: usize = 8 * 1024;
 
-        prev_state = current_state;
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+//add the token the FSM finished
+fn push(
+    tokens: &mut VecDeque<TokenType>,
+    names: &mut HashSet<Rc<str>>,
+    text: &str,
+    state: FsmTransitions,
+    span: Span,
+) -> Result<()> {
+    if state != _Operator {
+        tokens.push_back(token(names, text, state, span)?);
+        return Ok(());
     }
-    if current_state == _String {
-        current_state = get_string_type(current_token.clone());
+
+    //the FSM accepts any run of operator characters, split it into the language's
+    //  operators, longest first, so `a=-b` gives `=` `-` and `<<=` gives `<` `<=`
+    let mut rest = text;
+    let mut position = span.start;
+    while !rest.is_empty() {
+        //operator characters are all ascii and no operator is longer than two
+        let operator = (1..=rest.len().min(2))
+            .rev()
+            .find_map(|len| TokenKind::operator(&rest[..len]).map(|kind| (len, kind)));
+        let mut end = position;
+        match operator {
+            Some((len, kind)) => {
+                rest[..len].chars().for_each(|c| end.advance(c));
+                tokens.push_back(TokenType {
+                    kind,
+                    span: Span::new(position, end),
+                });
+                rest = &rest[len..];
+            }
+            None => {
+                rest.chars().for_each(|c| end.advance(c));
+                return Err(CompileError::Lexical {
+                    location: Location::from(Span::new(position, end)),
+                    found: rest.to_string(),
+                    message: "no operator matches the start of".to_string(),
+                });
+            }
+        }
+        position = end;
     }
-    if current_state != _Space && current_token != "" {
-        access.token = current_token.clone();
-        access.lexeme = current_state;
-        access.lexeme_name = get_lexeme_name(&access.lexeme);
-        access.line = line_count;
-        tokens.push(access.clone());
+    Ok(())
+}
+
+//a finished token, strings are told apart into keywords and identifiers and
+//  numbers are read into their values here
+fn token(
+    names: &mut HashSet<Rc<str>>,
+    text: &str,
+    state: FsmTransitions,
+    span: Span,
+) -> Result<TokenType> {
+    let kind = match state {
+        _String => match TokenKind::keyword(text) {
+            Some(kind) => kind,
+            None => TokenKind::Identifier(intern(names, text)),
+        },
+        //the FSM only accepts digits here, so only a value too big for any
+        //  int can fail
+        _Integer => match text.parse() {
+            Ok(value) => TokenKind::Integer(value),
+            Err(_) => {
+                return Err(CompileError::Lexical {
+                    location: Location::from(span),
+                    found: text.to_string(),
+                    message: "integer literal is too large".to_string(),
+                })
+            }
+        },
+        //digits around a single dot, which does not parse when there are no
+        //  digits and parses to infinity when the value is too big for a float
+        _Real => match text.parse::<f64>() {
+            Ok(value) if value.is_finite() => TokenKind::Real(value),
+            Ok(_) => {
+                return Err(CompileError::Lexical {
+                    location: Location::from(span),
+                    found: text.to_string(),
+                    message: "real literal is too large".to_string(),
+                })
+            }
+            Err(_) => {
+                return Err(CompileError::Lexical {
+                    location: Location::from(span),
+                    found: text.to_string(),
+                    message: "real number has no digits".to_string(),
+                })
+            }
+        },
+        _Separator => match text.chars().next().and_then(TokenKind::separator) {
+            Some(kind) => kind,
+            None => return Err(unknown_token(text, span)),
+        },
+        _ => return Err(unknown_token(text, span)),
+    };
+    Ok(TokenType { kind, span })
+}
+
+//the shared copy of an identifier name, made the first time the name is seen
+fn intern(names: &mut HashSet<Rc<str>>, name: &str) -> Rc<str> {
+    if let Some(interned) = names.get(name) {
+        return Rc::clone(interned);
     }
+    let interned: Rc<str> = Rc::from(name);
+    names.insert(Rc::clone(&interned));
+    interned
+}
 
-    tokens
+fn unknown_token(token: &str, span: Span) -> CompileError {
+    CompileError::Lexical {
+        location: Location::from(span),
+        found: token.to_string(),
+        message: "unrecognized token".to_string(),
+    }
 }
 
 //use our state table and enums with match to make pretty things out of ugly code
 fn get_col(c: char) -> FsmTransitions {
-    if c.is_digit(10) {
+    if c.is_ascii_digit() {
         _Integer
     } else if c.is_alphabetic() {
         _String
@@ -124,34 +365,138 @@ fn get_col(c: char) -> FsmTransitions {
     }
 }
 
-//use enums with match to make pretty things out of ugly code
-fn get_lexeme_name(lexeme: &FsmTransitions) -> String {
-    match lexeme {
-        _Comment => "COMMENT".to_string(),
-        _Space => "SPACE".to_string(),
-        _Separator => "SEPARATOR".to_string(),
-        _Integer => "INTEGER".to_string(),
-        _Real => "REAL".to_string(),
-        _String => "STRING".to_string(),
-        _Operator => "OPERATOR".to_string(),
-        _Unknown => "UNKNOWN".to_string(),
-        _Keyword => "KEYWORD".to_string(),
-        _Identifier => "IDENTIFIER".to_string(),
-        _ => "ERROR".to_string(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(offset: usize, line: usize, column: usize) -> Position {
+        Position {
+            offset,
+            line,
+            column,
+        }
+    }
+
+    fn spans(source: &str) -> Vec<(Position, Position)> {
+        lexer(source)
+            .unwrap()
+            .iter()
+            .map(|token| (token.span.start, token.span.end))
+            .collect()
     }
-}
 
-//pick out keywords and return the correct transition to the FSM
-fn get_string_type(token: String) -> FsmTransitions {
-    let keyword_vec = vec![
-        "int", "float", "bool", "true", "false", "if", "else", "then", "endif", "while",
-        "whileend", "do", "doend", "for", "forend", "input", "output", "and", "or", "not", "begin",
-        "end",
-    ];
+    #[test]
+    fn crlf_spans() {
+        assert_eq!(
+            spans("a\r\nbc\r\n"),
+            vec![
+                (at(0, 1, 1), at(1, 1, 2)),
+                (at(3, 2, 1), at(5, 2, 3)),
+                (at(7, 3, 1), at(7, 3, 1)),
+            ]
+        );
+    }
 
-    if keyword_vec.contains(&token.as_str()) {
-        return _Keyword;
-    } else {
-        return _Identifier;
-    };
+    #[test]
+    fn tab_spans() {
+        assert_eq!(
+            spans("\tx\t=\t12"),
+            vec![
+                (at(1, 1, 2), at(2, 1, 3)),
+                (at(3, 1, 4), at(4, 1, 5)),
+                (at(5, 1, 6), at(7, 1, 8)),
+                (at(7, 1, 8), at(7, 1, 8)),
+            ]
+        );
+    }
+
+    //a comment can run over several lines, the next token starts after it
+    #[test]
+    fn multi_line_comment() {
+        assert_eq!(
+            spans("a ! one\ntwo\n ! b"),
+            vec![
+                (at(0, 1, 1), at(1, 1, 2)),
+                (at(15, 3, 4), at(16, 3, 5)),
+                (at(16, 3, 5), at(16, 3, 5)),
+            ]
+        );
+    }
+
+    //offsets are bytes into the source, columns are characters
+    #[test]
+    fn offset_and_column() {
+        assert_eq!(
+            spans("ünï = 1"),
+            vec![
+                (at(0, 1, 1), at(5, 1, 4)),
+                (at(6, 1, 5), at(7, 1, 6)),
+                (at(8, 1, 7), at(9, 1, 8)),
+                (at(9, 1, 8), at(9, 1, 8)),
+            ]
+        );
+    }
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        lexer(source)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    fn name(name: &str) -> TokenKind {
+        TokenKind::Identifier(Rc::from(name))
+    }
+
+    //a run of operator characters splits into operators, longest first
+    #[test]
+    fn operator_runs() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("a=-b"),
+            vec![name("a"), Assign, Minus, name("b"), Eof]
+        );
+        assert_eq!(kinds("<<="), vec![Less, LessEqual, Eof]);
+        assert_eq!(kinds("a=<b"), vec![name("a"), Assign, Less, name("b"), Eof]);
+        assert_eq!(kinds("==-1"), vec![EqualEqual, Minus, Integer(1), Eof]);
+        //a run the input ends on is split the same way
+        assert_eq!(kinds("a<>="), vec![name("a"), NotEqual, Assign, Eof]);
+    }
+
+    //each operator of a run gets its own part of the run's span
+    #[test]
+    fn operator_run_spans() {
+        assert_eq!(
+            spans("a<<=b"),
+            vec![
+                (at(0, 1, 1), at(1, 1, 2)),
+                (at(1, 1, 2), at(2, 1, 3)),
+                (at(2, 1, 3), at(4, 1, 5)),
+                (at(4, 1, 5), at(5, 1, 6)),
+                (at(5, 1, 6), at(5, 1, 6)),
+            ]
+        );
+        assert_eq!(
+            spans("x =-"),
+            vec![
+                (at(0, 1, 1), at(1, 1, 2)),
+                (at(2, 1, 3), at(3, 1, 4)),
+                (at(3, 1, 4), at(4, 1, 5)),
+                (at(4, 1, 5), at(4, 1, 5)),
+            ]
+        );
+    }
+
+    //the error points at the opening '!' and covers nothing
+    #[test]
+    fn unterminated_comment() {
+        let error = lexer("a\n\t! never\nclosed").unwrap_err();
+        assert_eq!(
+            error.message(),
+            "comment is never closed, expected a matching `!`"
+        );
+        let span = error.location().unwrap().span;
+        assert_eq!((span.start, span.end), (at(3, 2, 2), at(3, 2, 2)));
+    }
 }
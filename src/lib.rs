@@ -0,0 +1,199 @@
+//! Compiler for the CPSC 323 "Rat" language.
+//!
+//! The pipeline is exposed as a library so other tools can drive it directly:
+//! [`compile`] runs the lexer and the table driven parser over a source string
+//! and hands back the tokens, rule trace, symbol table and diagnostics as
+//! values. The individual stages are also public through [`lexer`],
+//! [`parser`], [`semantic`], [`symbol_table`] and [`codegen`], [`vm`] runs the
+//! generated code, [`interpreter`] runs the syntax tree directly,
+//! [`c_backend`] translates it to C, and the language itself is described as
+//! data in [`grammar`]. For input too large to hold at once,
+//! [`lexer::Tokens`] reads tokens from any `BufRead` as they are needed and
+//! [`parser::parse_tokens`] parses them as they arrive.
+
+#[macro_use]
+extern crate prettytable;
+
+pub mod ast;
+pub mod c_backend;
+pub mod codegen;
+pub mod diagnostics;
+pub mod error;
+#[path = "file_handling/file_handling.rs"]
+pub mod file_handling;
+#[path = "lexer/fsm.rs"]
+pub mod fsm;
+pub mod grammar;
+pub mod interpreter;
+#[path = "lexer/lexer.rs"]
+pub mod lexer;
+pub mod parser;
+pub mod semantic;
+pub mod symbol_table;
+#[path = "tokens/tokens.rs"]
+pub mod tokens;
+pub mod vm;
+
+pub use ast::Program;
+pub use codegen::Instruction;
+pub use error::{CompileError, Location};
+pub use symbol_table::{Symbol, SymbolTable};
+pub use tokens::TokenType;
+
+use parser::{ParseFailure, ParseOutput};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+//how far through the pipeline compile() goes
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Lex,     // token and lexeme listing
+    Parse,   // production rule trace
+    Symbols, // rule trace followed by the symbol table, after semantic checks
+    Code,    // everything above plus the stack machine instruction listing
+}
+
+#[derive(Clone, Debug)]
+pub struct CompileOptions {
+    pub stage: Stage,
+    pub file_name: Option<String>, // used to label diagnostics
+    pub error_limit: usize,        // stop parsing after this many errors, 0 for no limit
+    pub base_address: usize,       // memory address of the first declared variable
+}
+
+impl Default for CompileOptions {
+    fn default() -> CompileOptions {
+        CompileOptions {
+            stage: Stage::Code,
+            file_name: None,
+            error_limit: parser::DEFAULT_ERROR_LIMIT,
+            base_address: symbol_table::DEFAULT_BASE_ADDRESS,
+        }
+    }
+}
+
+//everything produced by one run of the pipeline
+//  later stages stay empty when `stage` stops before them
+#[derive(Debug, Default)]
+pub struct CompileResult {
+    pub tokens: Vec<TokenType>,
+    pub rules: Vec<String>,
+    pub symbols: SymbolTable,
+    pub instructions: Vec<Instruction>,
+    pub program: Option<Program>,
+    pub diagnostics: Vec<CompileError>,
+    pub truncated: bool, // the parser stopped at the error limit
+}
+
+impl CompileResult {
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+//run the source text through the pipeline up to the requested stage
+pub fn compile(source: &str, options: &CompileOptions) -> CompileResult {
+    let mut result = CompileResult::default();
+
+    match lexer::lexer(source) {
+        Ok(tokens) => result.tokens = tokens,
+        Err(e) => {
+            result.diagnostics.push(label(e, options));
+            return result;
+        }
+    }
+
+    if options.stage == Stage::Lex {
+        return result;
+    }
+
+    let parsed = parser::parse(&result.tokens, options.error_limit);
+    check(&mut result, parsed, options);
+    result
+}
+
+//the same pipeline over source read a piece at a time, the parser takes each
+//  token as the lexer produces it
+//  gives the same result as compile() on the whole text, except that tokens are
+//  only kept for Stage::Lex
+pub fn compile_reader(input: &mut dyn BufRead, options: &CompileOptions) -> CompileResult {
+    let mut result = CompileResult::default();
+    let mut tokens = lexer::Tokens::new(input);
+    if let Some(name) = &options.file_name {
+        tokens = tokens.with_file(name);
+    }
+
+    if options.stage == Stage::Lex {
+        match tokens.collect() {
+            Ok(tokens) => result.tokens = tokens,
+            Err(e) => result.diagnostics.push(label(e, options)),
+        }
+        return result;
+    }
+
+    let parsed = parser::parse_tokens(tokens, options.error_limit);
+    check(&mut result, parsed, options);
+    result
+}
+
+//compile a source file, naming it in any diagnostics
+//  the file is streamed through the lexer, `-` reads standard input
+//  Err is only returned when the file cannot be opened
+pub fn compile_file(path: &str, options: &CompileOptions) -> Result<CompileResult, CompileError> {
+    let path = path.trim();
+    let options = CompileOptions {
+        file_name: Some(options.file_name.clone().unwrap_or_else(|| match path {
+            "-" => "<stdin>".to_string(),
+            _ => path.to_string(),
+        })),
+        ..options.clone()
+    };
+    if path == "-" {
+        let stdin = io::stdin();
+        return Ok(compile_reader(&mut stdin.lock(), &options));
+    }
+    let file = File::open(path).map_err(|e| CompileError::io(path, e))?;
+    Ok(compile_reader(&mut BufReader::new(file), &options))
+}
+
+//everything after the parse: semantic checks and code generation, as far as the
+//  stage asks for
+fn check(
+    result: &mut CompileResult,
+    parsed: Result<ParseOutput, ParseFailure>,
+    options: &CompileOptions,
+) {
+    match parsed {
+        Ok(parsed) => {
+            result.rules = parsed.trace;
+            if options.stage >= Stage::Symbols {
+                result.symbols = SymbolTable::new(options.base_address);
+                let errors = semantic::check(&parsed.program, &mut result.symbols);
+                let labelled = errors.into_iter().map(|e| label(e, options));
+                result.diagnostics.extend(labelled);
+            }
+            if options.stage >= Stage::Code && result.is_ok() {
+                match codegen::generate(&parsed.program, &result.symbols) {
+                    Ok(code) => result.instructions = code,
+                    Err(errors) => {
+                        let labelled = errors.into_iter().map(|e| label(e, options));
+                        result.diagnostics.extend(labelled);
+                    }
+                }
+            }
+            result.program = Some(parsed.program);
+        }
+        Err(failure) => {
+            let labelled = failure.errors.into_iter().map(|e| label(e, options));
+            result.diagnostics.extend(labelled);
+            result.truncated = failure.truncated;
+        }
+    }
+}
+
+fn label(error: CompileError, options: &CompileOptions) -> CompileError {
+    match &options.file_name {
+        Some(name) => error.with_file(name),
+        None => error,
+    }
+}
@@ -1,24 +1,271 @@
-#[macro_use] extern crate prettytable;
-#[path = "file_handling/file_handling.rs"]
-mod file_handling;
-mod parser;
-use std::process::Command;
-//use file_handling::tokens::*; not needed right now
+mod cli;
+use cpsc_323_compiler_project::c_backend;
+use cpsc_323_compiler_project::diagnostics::{self, Renderer};
+use cpsc_323_compiler_project::file_handling;
+use cpsc_323_compiler_project::interpreter;
+use cpsc_323_compiler_project::vm;
+use cpsc_323_compiler_project::{compile_file, CompileError, CompileOptions, CompileResult, Stage};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, Write as IoWrite};
+use std::process;
 
 fn main() {
-    let mut count: i32 = 0; //initial arg count from cli
-    //get file contents as a string
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    let expression =
-        file_handling::convert_file_to_string(file_handling::get_file_name(&mut count));
+    //with no arguments fall back to asking for the paths like before
+    let options = if args.is_empty() {
+        match prompt_options() {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        match cli::parse_args(&args) {
+            Ok(options) => options,
+            Err(msg) => {
+                eprintln!("error: {}\n\n{}", msg, cli::USAGE);
+                process::exit(2);
+            }
+        }
+    };
 
-    //get vector of tokens from the lexer module
-    let token: Vec<file_handling::lexer::TokenType> =
-        file_handling::lexer::lexer(&expression);
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
 
-    //write vector of tokens to specified file
-    //file_handling::write_to_file(file_handling::get_file_name(&mut count), token);
+    //exit status 1 when any input failed to compile or run
+    let outcome = match options.command {
+        cli::Command::Compile => run(&options),
+        cli::Command::Run | cli::Command::Interpret => execute(&options),
+    };
+    match outcome {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+//compile every input and write the requested stage's results
+//  Ok(false) means at least one input had diagnostics
+fn run(options: &cli::Options) -> Result<bool, CompileError> {
+    //results go to the output file when one is given, otherwise stdout
+    //  the file is opened once there is something to write, so a failed compile
+    //  leaves it alone
+    let output_name = match &options.output {
+        Some(path) => path.trim().to_string(),
+        None => "<stdout>".to_string(),
+    };
+    let mut out: Option<Box<dyn IoWrite>> = None;
+
+    let renderer = Renderer::new(options.color);
+    let mut success = true;
+
+    //C is translated from the checked program, the stage listings are not needed
+    let stage = match options.emit {
+        cli::Emit::C => Stage::Symbols,
+        cli::Emit::Stages => options.stage,
+    };
+    let compile_options = compile_options(options, stage);
+
+    for input in options.inputs.iter() {
+        let mut result = compile_file(input, &compile_options)?;
+        let c_source = match options.emit {
+            cli::Emit::C if result.is_ok() => emit_c(&mut result, &input_name(input)),
+            _ => None,
+        };
+        if result.is_ok() {
+            let out = match &mut out {
+                Some(out) => out,
+                None => out.insert(open_output(options, &output_name)?),
+            };
+            let written = match &c_source {
+                Some(c_source) => out.write_all(c_source.as_bytes()),
+                None => write_results(out, input, options, &result),
+            };
+            written.map_err(|e| CompileError::io(&output_name, e))?;
+        } else {
+            let source = source_text(input);
+            eprint!(
+                "{}",
+                diagnostics::render_all(&renderer, &result.diagnostics, &source)
+            );
+            if result.truncated {
+                let plural = if options.error_limit == 1 { "" } else { "s" };
+                eprintln!(
+                    "error: stopped after {} error{}",
+                    options.error_limit, plural
+                );
+            }
+            success = false;
+        }
+    }
+
+    Ok(success)
+}
+
+//compile every input and run it, one program after the other
+//  run executes the generated code, interpret evaluates the syntax tree and
+//  skips code generation
+//  Ok(false) means an input had diagnostics or stopped with a runtime error
+fn execute(options: &cli::Options) -> Result<bool, CompileError> {
+    //STDIN and STDOUT go to the terminal unless files are given
+    //  STDIN is opened once the first program runs, a `-` input has been read
+    //  from the terminal by then
+    let mut input: Option<Box<dyn BufRead>> = None;
+    let mut output: Box<dyn IoWrite> = match &options.stdout {
+        Some(path) => Box::new(File::create(path).map_err(|e| CompileError::io(path, e))?),
+        None => Box::new(io::stdout()),
+    };
+
+    let renderer = Renderer::new(options.color);
+
+    let stage = match options.command {
+        cli::Command::Interpret => Stage::Symbols,
+        _ => Stage::Code,
+    };
+    let compile_options = compile_options(options, stage);
+
+    for file in options.inputs.iter() {
+        let result = compile_file(file, &compile_options)?;
+
+        if !result.is_ok() {
+            let source = source_text(file);
+            eprint!(
+                "{}",
+                diagnostics::render_all(&renderer, &result.diagnostics, &source)
+            );
+            return Ok(false);
+        }
+
+        let input = match &mut input {
+            Some(input) => input,
+            None => input.insert(open_stdin(options)?),
+        };
+        let ran = match (&result.program, options.command) {
+            (Some(program), cli::Command::Interpret) => {
+                interpreter::run(program, &result.symbols, input, &mut output)
+            }
+            _ => vm::run(&result.instructions, &result.symbols, input, &mut output),
+        };
+        if let Err(e) = ran {
+            let e = e.with_file(&input_name(file));
+            eprint!("{}", renderer.render(&e, &source_text(file)));
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+//what the library needs to know from the command line, compile_file names
+//  each input in its diagnostics
+fn compile_options(options: &cli::Options, stage: Stage) -> CompileOptions {
+    CompileOptions {
+        stage,
+        error_limit: options.error_limit,
+        base_address: options.base_address,
+        ..CompileOptions::default()
+    }
+}
+
+//name an input goes by in diagnostics, as compile_file names it
+fn input_name(input: &str) -> String {
+    match input.trim() {
+        "-" => "<stdin>".to_string(),
+        path => path.to_string(),
+    }
+}
+
+//source text to show under diagnostics, read again only once something failed
+//  standard input cannot be read twice, its diagnostics show just the location
+fn source_text(input: &str) -> String {
+    match input.trim() {
+        "-" => String::new(),
+        path => file_handling::convert_file_to_string(path.to_string()).unwrap_or_default(),
+    }
+}
+
+//stage listings are appended to the output file, a C translation replaces it
+fn open_output(options: &cli::Options, name: &str) -> Result<Box<dyn IoWrite>, CompileError> {
+    if options.output.is_none() {
+        return Ok(Box::new(io::stdout()));
+    }
+    let file = OpenOptions::new()
+        .append(options.emit == cli::Emit::Stages)
+        .write(true)
+        .truncate(options.emit == cli::Emit::C)
+        .create(true)
+        .open(name)
+        .map_err(|e| CompileError::io(name, e))?;
+    Ok(Box::new(file))
+}
+
+fn open_stdin(options: &cli::Options) -> Result<Box<dyn BufRead>, CompileError> {
+    match &options.stdin {
+        Some(path) => Ok(Box::new(BufReader::new(
+            File::open(path).map_err(|e| CompileError::io(path, e))?,
+        ))),
+        None => Ok(Box::new(io::stdin().lock())),
+    }
+}
+
+//translate a checked program to C, adding any problems to its diagnostics
+fn emit_c(result: &mut CompileResult, file: &str) -> Option<String> {
+    let program = result.program.as_ref()?;
+    match c_backend::generate(program, &result.symbols) {
+        Ok(source) => Some(source),
+        Err(errors) => {
+            let labelled = errors.into_iter().map(|e| e.with_file(file));
+            result.diagnostics.extend(labelled);
+            None
+        }
+    }
+}
+
+fn write_results(
+    out: &mut dyn IoWrite,
+    input: &str,
+    options: &cli::Options,
+    result: &CompileResult,
+) -> io::Result<()> {
+    //label each section when several files share one output
+    if options.inputs.len() > 1 {
+        writeln!(out, "==> {} <==", input.trim())?;
+    }
+
+    match options.stage {
+        Stage::Lex => file_handling::write_tokens(out, &result.tokens),
+        Stage::Parse => file_handling::write_rules(out, &result.rules),
+        Stage::Symbols => {
+            file_handling::write_rules(out, &result.rules)?;
+            result.symbols.print_table(out)
+        }
+        Stage::Code => {
+            file_handling::write_rules(out, &result.rules)?;
+            result.symbols.print_table(out)?;
+            file_handling::write_instructions(out, &result.instructions)
+        }
+    }
+}
+
+//interactive mode: ask for one input and one output path on stdin
+fn prompt_options() -> Result<cli::Options, CompileError> {
+    let mut count: i32 = 0;
+    let input = file_handling::get_file_name(&mut count)?;
+    count = 1;
+    let output = file_handling::get_file_name(&mut count)?;
 
-    parser::parse(token);
-    
+    Ok(cli::Options {
+        inputs: vec![input],
+        output: Some(output),
+        ..cli::Options::default()
+    })
 }
@@ -1,700 +1,494 @@
-use crate::file_handling;
-use crate::file_handling::lexer::*;
-use prettytable::{Cell, Row, Table};
-use std::collections::HashMap; //hashmapping
-use std::fs::OpenOptions;
-use std::io::Write as IoWrite;
+use crate::ast::{self, Program};
+use crate::error::{CompileError, Location, Result};
+pub use crate::grammar::Symbols;
+use crate::grammar::{self, ParseTable};
+use crate::tokens::Span;
+use crate::tokens::{TokenKind, TokenType};
 use Symbols::*;
-//derive operations to perform deep copies of the enum later
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub enum Symbols {
-    // Terminals
-    PLUS,    // +
-    MINUS,   // -
-    MULT,    // *
-    DIV,     // /
-    L_PAREN, // (
-    R_PAREN, // )
-    NUM,     // num
-    ID,      // id
-    EQUAL,
-    INT,
-    BOOL,
-    FLOAT,
-    COMMA,
-    SEMICOLON,
-    IF,
-    THEN,
-    ELSE,
-    ENDIF,
-    WHILE,
-    DO,
-    WHILEEND,
-    BEGIN,
-    END,
-    LTHAN,
-    LEQUAL,
-    EQUALTO,
-    NOTEQUAL,
-    GTHANEQUAL,
-    GTHAN,
-    ERROR,
-    END_OF_STACK, // $
-
-    // Non-Terminals             RULE#
-    EXPR,       // TE'                          1
-    EXPR_PRIME, // +TE' | -TE' | EPSILON        2,3,4
-    TERM,       // F | T'                       5,6
-    TERM_PRIME, // *FT' | /FT' | E              7,8, 13
-    FACTOR,     // (E) | ID | <NUM>             9,10,11
-    ID_NT,      // ID                         12
-    STATEMENT,  // S
-    ASSIGN,
-    DECLAR,
-    TYPE,
-    MOREIDS,
-    MORESTATEMENTS,
-    CONDITIONAL,
-    RELOP,
-}
 
 pub fn lexer_to_symbol(current_token: &TokenType) -> Symbols {
     //match token to symbol enum
-    match current_token.lexeme_name.as_str() {
-        "IDENTIFIER" => match current_token.token.as_str() {
-            "$" => END_OF_STACK,
-            _ => ID,
-        },
-
-        "SEPARATOR" => {
-            //match against parenthesis
-            match current_token.token.as_str() {
-                "(" => L_PAREN,
-                ")" => R_PAREN,
-                ";" => SEMICOLON,
-                "," => COMMA,
-
-                _ => ERROR,
+    match current_token.kind {
+        TokenKind::Identifier(_) => ID,
+        TokenKind::Integer(_) => NUM,
+        TokenKind::Real(_) => REAL,
+        TokenKind::Eof => END_OF_STACK,
+
+        TokenKind::LParen => L_PAREN,
+        TokenKind::RParen => R_PAREN,
+        TokenKind::Semicolon => SEMICOLON,
+        TokenKind::Comma => COMMA,
+
+        TokenKind::Plus => PLUS,
+        TokenKind::Minus => MINUS,
+        TokenKind::Star => MULT,
+        TokenKind::Slash => DIV,
+        TokenKind::Assign => EQUAL,
+        TokenKind::Less => LTHAN,
+        TokenKind::LessEqual => LEQUAL,
+        TokenKind::EqualEqual => EQUALTO,
+        TokenKind::NotEqual => NOTEQUAL,
+        TokenKind::GreaterEqual => GTHANEQUAL,
+        TokenKind::Greater => GTHAN,
+
+        TokenKind::Int => INT,
+        TokenKind::Bool => BOOL,
+        TokenKind::Float => FLOAT,
+        TokenKind::If => IF,
+        TokenKind::Then => THEN,
+        TokenKind::Else => ELSE,
+        TokenKind::Endif => ENDIF,
+        TokenKind::While => WHILE,
+        TokenKind::Do => DO,
+        TokenKind::Whileend => WHILEEND,
+        TokenKind::For => FOR,
+        TokenKind::Forend => FOREND,
+        TokenKind::Doend => DOEND,
+        TokenKind::Begin => BEGIN,
+        TokenKind::End => END,
+        TokenKind::Input => INPUT,
+        TokenKind::Output => OUTPUT,
+        TokenKind::And => AND,
+        TokenKind::Or => OR,
+        TokenKind::Not => NOT,
+        TokenKind::True => TRUE,
+        TokenKind::False => FALSE,
+
+        //lexed but not part of the grammar
+        TokenKind::Percent
+        | TokenKind::LBrace
+        | TokenKind::RBrace
+        | TokenKind::LBracket
+        | TokenKind::RBracket
+        | TokenKind::Colon
+        | TokenKind::Quote => ERROR,
+    }
+}
+
+//node of the concrete parse tree, one per symbol the parser pushed
+#[derive(Clone, Debug)]
+pub struct ParseNode {
+    pub symbol: Symbols,
+    pub token: Option<TokenType>, // the matched token, for terminals
+    pub children: Vec<usize>,     // indexes into ParseTree::nodes, in grammar order
+    pub span: Option<Span>,       // source covered, None when no token is under the node
+}
+
+//parse tree built alongside the symbol stack
+#[derive(Clone, Debug, Default)]
+pub struct ParseTree {
+    pub nodes: Vec<ParseNode>,
+    pub root: usize,
+}
+
+impl ParseTree {
+    fn add(&mut self, symbol: Symbols) -> usize {
+        self.nodes.push(ParseNode {
+            symbol,
+            token: None,
+            children: Vec::new(),
+            span: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    //fill in every node's span once the parse is done
+    //  children are always added after their parent, so walking the nodes
+    //  backwards reaches each child before the node above it, without recursing
+    //  down long chains like a sum of thousands of terms
+    fn measure(&mut self) {
+        for id in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[id];
+            let span = match &node.token {
+                Some(token) => Some(token.span),
+                None => node
+                    .children
+                    .iter()
+                    .filter_map(|child| self.nodes[*child].span)
+                    .reduce(Span::to),
+            };
+            self.nodes[id].span = span;
+        }
+    }
+
+    //leftmost token under a node
+    pub fn first_token(&self, id: usize) -> Option<&TokenType> {
+        let mut id = id;
+        loop {
+            let node = &self.nodes[id];
+            if node.token.is_some() {
+                return node.token.as_ref();
+            }
+            id = *node
+                .children
+                .iter()
+                .find(|child| self.nodes[**child].span.is_some())?;
+        }
+    }
+
+    //rightmost token under a node
+    pub fn last_token(&self, id: usize) -> Option<&TokenType> {
+        let mut id = id;
+        loop {
+            let node = &self.nodes[id];
+            if node.token.is_some() {
+                return node.token.as_ref();
             }
+            id = *node
+                .children
+                .iter()
+                .rev()
+                .find(|child| self.nodes[**child].span.is_some())?;
         }
+    }
 
-        "OPERATOR" => match current_token.token.as_str() {
-            "+" => PLUS,
-            "-" => MINUS,
-            "*" => MULT,
-            "/" => DIV,
-            "=" => EQUAL,
-            "<" => LTHAN,
-            "<=" => LEQUAL,
-            "==" => EQUALTO,
-            "<>" => NOTEQUAL,
-            ">=" => GTHANEQUAL,
-            ">" => GTHAN,
-
-            _ => ERROR,
-        },
-
-        "INTEGER" => NUM,
-
-        "KEYWORD" => match current_token.token.as_str() {
-            "int" => INT,
-            "bool" => BOOL,
-            "float" => FLOAT,
-            "if" => IF,
-            "then" => THEN,
-            "else" => ELSE,
-            "endif" => ENDIF,
-            "while" => WHILE,
-            "do" => DO,
-            "whileend" => WHILEEND,
-            "begin" => BEGIN,
-            "end" => END,
-            _ => ERROR,
-        },
-
-        _ => ERROR,
+    //source covered by a node, empty nodes get a default span
+    pub fn span(&self, id: usize) -> Span {
+        self.nodes[id].span.unwrap_or_default()
     }
 }
 
-pub fn parse(token_list: Vec<TokenType>) {
-    //create hash map
-    let mut LL_TABLE = HashMap::new();
-
-    //create EXPR row
-    LL_TABLE.insert((STATEMENT, L_PAREN), 13);
-    LL_TABLE.insert((STATEMENT, NUM), 1);
-    LL_TABLE.insert((STATEMENT, ID), 13);
-    LL_TABLE.insert((STATEMENT, INT), 15);
-    LL_TABLE.insert((STATEMENT, BOOL), 15);
-    LL_TABLE.insert((STATEMENT, FLOAT), 15);
-    LL_TABLE.insert((STATEMENT, IF), 23);
-    LL_TABLE.insert((STATEMENT, ELSE), 4);
-    LL_TABLE.insert((STATEMENT, ENDIF), 4);
-    LL_TABLE.insert((STATEMENT, WHILE), 24);
-    LL_TABLE.insert((STATEMENT, WHILEEND), 4);
-    LL_TABLE.insert((STATEMENT, BEGIN), 25);
-    LL_TABLE.insert((STATEMENT, END), 4);
-    LL_TABLE.insert((STATEMENT, SEMICOLON), 4);
-    LL_TABLE.insert((STATEMENT, END_OF_STACK), 4);
-    LL_TABLE.insert((MORESTATEMENTS, SEMICOLON), 26);
-    LL_TABLE.insert((MORESTATEMENTS, WHILEEND), 4);
-    LL_TABLE.insert((MORESTATEMENTS, ID), 13);
-    LL_TABLE.insert((MORESTATEMENTS, END), 4);
-    LL_TABLE.insert((MOREIDS, COMMA), 36);
-    LL_TABLE.insert((MOREIDS, SEMICOLON), 4);
-    LL_TABLE.insert((ASSIGN, ID), 14);
-    LL_TABLE.insert((DECLAR, INT), 16);
-    LL_TABLE.insert((DECLAR, BOOL), 16);
-    LL_TABLE.insert((DECLAR, FLOAT), 16);
-    LL_TABLE.insert((TYPE, INT), 17);
-    LL_TABLE.insert((TYPE, BOOL), 18);
-    LL_TABLE.insert((TYPE, FLOAT), 19);
-    LL_TABLE.insert((CONDITIONAL, ID), 27);
-    LL_TABLE.insert((CONDITIONAL, NUM), 27);
-    LL_TABLE.insert((RELOP, LTHAN), 28);
-    LL_TABLE.insert((RELOP, LEQUAL), 29);
-    LL_TABLE.insert((RELOP, EQUALTO), 30);
-    LL_TABLE.insert((RELOP, NOTEQUAL), 31);
-    LL_TABLE.insert((RELOP, GTHANEQUAL), 32);
-    LL_TABLE.insert((RELOP, GTHAN), 33);
-    LL_TABLE.insert((EXPR, L_PAREN), 1);
-    LL_TABLE.insert((EXPR, ID), 1);
-    LL_TABLE.insert((EXPR, NUM), 1);
-    LL_TABLE.insert((EXPR_PRIME, PLUS), 2);
-    LL_TABLE.insert((EXPR_PRIME, MINUS), 3);
-    LL_TABLE.insert((EXPR_PRIME, R_PAREN), 4);
-    LL_TABLE.insert((EXPR_PRIME, END_OF_STACK), 4);
-    LL_TABLE.insert((EXPR_PRIME, SEMICOLON), 20);
-    LL_TABLE.insert((EXPR_PRIME, MULT), 6);
-    LL_TABLE.insert((EXPR_PRIME, DIV), 6);
-    LL_TABLE.insert((EXPR_PRIME, THEN), 4);
-    LL_TABLE.insert((EXPR_PRIME, ELSE), 4);
-    LL_TABLE.insert((EXPR_PRIME, ENDIF), 4);
-    LL_TABLE.insert((EXPR_PRIME, LTHAN), 4);
-    LL_TABLE.insert((EXPR_PRIME, LEQUAL), 4);
-    LL_TABLE.insert((EXPR_PRIME, EQUALTO), 4);
-    LL_TABLE.insert((EXPR_PRIME, NOTEQUAL), 4);
-    LL_TABLE.insert((EXPR_PRIME, GTHANEQUAL), 4);
-    LL_TABLE.insert((EXPR_PRIME, GTHAN), 4);
-    LL_TABLE.insert((EXPR_PRIME, DO), 4);
-    LL_TABLE.insert((EXPR_PRIME, SEMICOLON), 4);
-    LL_TABLE.insert((TERM, L_PAREN), 5);
-    LL_TABLE.insert((TERM, NUM), 5);
-    LL_TABLE.insert((TERM, ID), 5);
-    LL_TABLE.insert((TERM, MULT), 6);
-    LL_TABLE.insert((TERM, DIV), 6);
-    LL_TABLE.insert((TERM_PRIME, PLUS), 4);
-    LL_TABLE.insert((TERM_PRIME, MINUS), 4);
-    LL_TABLE.insert((TERM_PRIME, MULT), 7);
-    LL_TABLE.insert((TERM_PRIME, DIV), 8);
-    LL_TABLE.insert((TERM_PRIME, R_PAREN), 4);
-    LL_TABLE.insert((TERM_PRIME, SEMICOLON), 4);
-    LL_TABLE.insert((TERM_PRIME, THEN), 4);
-    LL_TABLE.insert((TERM_PRIME, ELSE), 4);
-    LL_TABLE.insert((TERM_PRIME, ENDIF), 4);
-    LL_TABLE.insert((TERM_PRIME, LTHAN), 4);
-    LL_TABLE.insert((TERM_PRIME, LEQUAL), 4);
-    LL_TABLE.insert((TERM_PRIME, EQUALTO), 4);
-    LL_TABLE.insert((TERM_PRIME, NOTEQUAL), 4);
-    LL_TABLE.insert((TERM_PRIME, GTHANEQUAL), 4);
-    LL_TABLE.insert((TERM_PRIME, GTHAN), 4);
-    LL_TABLE.insert((TERM_PRIME, DO), 4);
-    LL_TABLE.insert((TERM_PRIME, END_OF_STACK), 4);
-    LL_TABLE.insert((FACTOR, L_PAREN), 9);
-    LL_TABLE.insert((FACTOR, ID), 10);
-    LL_TABLE.insert((FACTOR, NUM), 11);
-    LL_TABLE.insert((ID_NT, ID), 12);
-    LL_TABLE.insert((END_OF_STACK, SEMICOLON), 21);
+//everything the parser found out about a token list
+#[derive(Clone, Debug, Default)]
+pub struct ParseOutput {
+    pub trace: Vec<String>, // rule and match lines in the order they happened
+    pub tree: ParseTree,
+    pub program: Program,
+}
+
+//tokens that usually end a statement or block, used to resynchronise after an error
+const SYNC_SYMBOLS: [Symbols; 7] = [SEMICOLON, END, ENDIF, WHILEEND, FOREND, DOEND, END_OF_STACK];
+
+//error limit used by compile() when none is given
+pub const DEFAULT_ERROR_LIMIT: usize = 20;
+
+//run the table driven parser over the tokens, recording each rule and match
+//  after a syntax error the parser skips ahead to a synchronising token and keeps
+//  going, so every error in the file is reported
+//  gives up once `error_limit` errors have been found, 0 means no limit
+pub fn parse(
+    token_list: &[TokenType],
+    error_limit: usize,
+) -> std::result::Result<ParseOutput, ParseFailure> {
+    parse_tokens(token_list.iter().cloned().map(Ok), error_limit)
+}
+
+//the same parser, pulling each token only when it needs it, such as from a
+//  lexer::Tokens reading a file or a pipe
+//  a lexical error stops the parse and is the only error returned, as when the
+//  whole input is lexed before parsing
+pub fn parse_tokens<I>(
+    tokens: I,
+    error_limit: usize,
+) -> std::result::Result<ParseOutput, ParseFailure>
+where
+    I: IntoIterator<Item = Result<TokenType>>,
+{
+    let table = grammar::table();
+    let mut cursor = match Cursor::new(tokens.into_iter()) {
+        Ok(cursor) => cursor,
+        Err(e) => return Err(ParseFailure::from(vec![e])),
+    };
+
     //create symbol stack
+    //  slots holds the parse tree node of each symbol on the stack
     let mut ss: Vec<Symbols> = Vec::new();
-
-    let mut token_pointer: usize = 0;
+    let mut slots: Vec<usize> = Vec::new();
+    let mut tree = ParseTree::default();
 
     //push end of stack $
     ss.push(END_OF_STACK);
-    //push expression
-    ss.push(STATEMENT);
-
-    let mut ST: Vec<(String, String, usize)> = Vec::new();
-    let mut symbol_type: String = " ".to_string();
-    let mut symbol_name: String = " ".to_string();
-    let mut symbol_flag: bool = false;
-
-    let output_path: String = file_handling::get_file_name(&mut 1);
-    let mut file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(output_path.trim())
-        .unwrap();
-
-    while ss.len() > 0 {
+    slots.push(tree.add(END_OF_STACK));
+    //push the start symbol
+    ss.push(grammar::START);
+    tree.root = tree.add(grammar::START);
+    slots.push(tree.root);
+
+    let mut trace: Vec<String> = Vec::new();
+    let mut errors: Vec<CompileError> = Vec::new();
+    let mut last_error_at: Option<usize> = None;
+    let mut truncated = false;
+
+    while !ss.is_empty() {
         //let mut line = String::new();                                  //uncomment to
         //let b1 = std::io::stdin().read_line(&mut line).unwrap();      //loop one at a time
 
         //println!("Stack: {:?}", ss);                                 // uncomment to print current stack on command line
         //println!("Vector in now len: {:?}", ss.len());
 
-        if ss[ss.len() - 1] == END_OF_STACK && token_list.len() == token_pointer + 1 {
-            if let Err(e) = writeln!(file, "Parse successfully :) \n") {
-                eprintln!("Could not write to file: {}", e);
+        //ran out of tokens before the stack emptied
+        let symbol = match cursor.symbol() {
+            Some(symbol) => symbol,
+            None => {
+                errors.push(CompileError::Syntax {
+                    location: Location::from(cursor.last_span),
+                    expected: expected_symbols(&ss, table),
+                    found: "end of file".to_string(),
+                });
+                break;
+            }
+        };
+
+        if ss[ss.len() - 1] == END_OF_STACK && symbol == END_OF_STACK {
+            if errors.is_empty() {
+                trace.push("Parse successfully :) \n".to_string());
             }
             ss.pop();
+            slots.pop();
         }
         //compare the lexer at pointer to stack
-        else if ss[ss.len() - 1] == lexer_to_symbol(&token_list[token_pointer]) {
-            if let Err(e) = writeln!(file, "Match symbols: {:?}", token_list[token_pointer].token) {
-                eprintln!("Could not write to file: {}", e);
+        else if ss[ss.len() - 1] == symbol {
+            let token = cursor.current.clone();
+            if let Some(token) = &token {
+                trace.push(format!("Match symbols: {:?}", token.kind.to_string()));
             }
-            //increment token pointer
-            token_pointer = token_pointer + 1;
-
             //pop off front of vector stack
             ss.pop();
-        } else {
-            let mut current_symbol = lexer_to_symbol(&token_list[token_pointer]);
-            let mut nlength = ss.len() - 1;
-            //holds current rule cell (usize)
-            let mut current_table_cell = LL_TABLE.get(&(ss[nlength], current_symbol));
-
-            //output the rule
-            //println!("Rule: {:?}", current_table_cell);
-
-            //match correct rule
-            match current_table_cell {
-                //TE'
-                Some(1) => {
-                    //remvove front
-                    if let Err(e) = writeln!(file, "Rule: Expression⟶ Term ExpressionPrime") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(EXPR_PRIME);
-                    ss.push(TERM);
-                }
-
-                //+TE'
-                Some(2) => {
-                    if let Err(e) = writeln!(file, "ExpressionPrime⟶ + Term ExpressionPrime") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-
-                    //remove front
-                    ss.pop();
-                    //see above
-                    ss.push(EXPR_PRIME);
-                    ss.push(TERM);
-                    ss.push(PLUS);
-                }
-
-                //-TE'
-                Some(3) => {
-                    if let Err(e) = writeln!(file, "ExpressionPrime⟶ - Term ExpressionPrime") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(EXPR_PRIME);
-                    ss.push(TERM);
-                    ss.push(MINUS);
-                }
-
-                //EPSILON
-                Some(4) => {
-                    if let Err(e) = writeln!(file, "Rule: {:?} ⟶ ϵ", ss[nlength]) {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                }
-
-                //F
-                Some(5) => {
-                    if let Err(e) = writeln!(file, "Term⟶ Factor TermPrime") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(TERM_PRIME);
-                    ss.push(FACTOR);
-                }
-
-                //T'
-                Some(6) => {
-                    ss.pop();
-                    ss.push(TERM_PRIME);
-                }
-                //*FT'
-                Some(7) => {
-                    if let Err(e) = writeln!(file, "Rule: TermPrime⟶ * Factor TermPrime") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(TERM_PRIME);
-                    ss.push(FACTOR);
-                    ss.push(MULT);
-                }
-
-                //FT'
-                Some(8) => {
-                    if let Err(e) = writeln!(file, "Rule: TermPrime⟶ / Factor TermPrime") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(TERM_PRIME);
-                    ss.push(FACTOR);
-                    ss.push(DIV);
-                }
-
-                //(E)
-                Some(9) => {
-                    if let Err(e) = writeln!(file, "Rule: Factor⟶ ( Expression )") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    //remove front
-                    ss.pop();
-                    //add r_paren, expr, l_paren
-                    ss.push(R_PAREN);
-                    ss.push(EXPR);
-                    ss.push(L_PAREN);
-                }
-
-                //ID_NT
-                Some(10) => {
-                    if let Err(e) = writeln!(file, "Factor⟶ ID") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(Symbols::ID_NT);
-                }
-
-                // <NUM>
-                Some(11) => {
-                    if let Err(e) = writeln!(file, "Rule: Factor⟶ num") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(Symbols::NUM);
-                }
-
-                //id
-                Some(12) => {
-                    if let Err(e) = writeln!(file, "Rule: ID⟶ id") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(Symbols::ID);
-                    symbol_name = token_list[token_pointer].token.to_string();
-                    if symbol_flag {
-                        ST.push((
-                            symbol_type.clone(),
-                            symbol_name.clone(),
-                            token_list[token_pointer].line,
-                        ));
-                        symbol_flag = false;
-                    }
-                }
-
-                Some(13) => {
-                    //check to see if next token is an "="
-                    match token_list[token_pointer + 1].token.as_str() {
-                        "=" => {
-                            if let Err(e) = writeln!(file, "Rule: Statement⟶ Assign") {
-                                eprintln!("Could not write to file: {}", e);
-                            }
-                            ss.pop();
-                            ss.push(ASSIGN);
-                        }
-
-                        _ => {
-                            if let Err(e) = writeln!(file, "Rule: Statement⟶ Expression") {
-                                eprintln!("Could not write to file: {}", e);
-                            }
-                            ss.pop();
-                            ss.push(EXPR);
-                        }
-                    }
-                }
-
-                Some(14) => {
-                    if let Err(e) = writeln!(file, "Assign⟶ ID = Expression") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(EXPR);
-                    ss.push(EQUAL);
-                    ss.push(ID_NT);
-                }
-
-                Some(15) => {
-                    if let Err(e) = writeln!(file, "Rule: Statement ⟶ Declarative") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
+            if let Some(node) = slots.pop() {
+                tree.nodes[node].token = token;
+            }
 
-                    symbol_type = return_enum_string(current_symbol);
-                    ss.push(DECLAR);
-                    symbol_flag = true;
+            //move on to the next token
+            cursor.advance().map_err(|e| vec![e])?;
+        } else if let Some(production) = table.get(ss[ss.len() - 1], symbol) {
+            //output the rule and replace the top of the stack with its right side
+            trace.push(format!("Rule: {}", production));
+            expand(&mut ss, &mut slots, &mut tree, production.rhs);
+        } else {
+            //a second error on the same token means recovery made no
+            //  progress, so throw the token away instead of reporting it again
+            if last_error_at == Some(cursor.index) {
+                if symbol != END_OF_STACK {
+                    cursor.advance().map_err(|e| vec![e])?;
+                }
+            } else {
+                let found = cursor.current.as_ref().map(describe_token);
+                errors.push(CompileError::Syntax {
+                    location: Location::from(cursor.last_span),
+                    expected: expected_symbols(&ss, table),
+                    found: found.unwrap_or_default(),
+                });
+                if errors.len() == error_limit {
+                    truncated = true;
+                    break;
                 }
+            }
+            last_error_at = Some(cursor.index);
 
-                Some(16) => {
-                    if let Err(e) = writeln!(file, "Rule: Declarative⟶ Type ID MoreIds;") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(SEMICOLON);
-                    ss.push(MOREIDS);
-                    ss.push(ID_NT);
-                    ss.push(TYPE);
-                }
+            recover(&mut ss, &mut slots, &mut cursor, table).map_err(|e| vec![e])?;
+        }
+    }
 
-                Some(17) => {
-                    if let Err(e) = writeln!(file, "Rule: Type⟶ int") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(INT);
-                }
+    //a lexical error further on still wins, as it does when the whole input is
+    //  lexed before parsing
+    while cursor.current.is_some() {
+        cursor.advance().map_err(|e| vec![e])?;
+    }
 
-                Some(18) => {
-                    if let Err(e) = writeln!(file, "Rule: Type⟶ bool") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(BOOL);
-                }
+    if !errors.is_empty() {
+        return Err(ParseFailure { errors, truncated });
+    }
 
-                Some(19) => {
-                    if let Err(e) = writeln!(file, "Rule: Type⟶ float") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(FLOAT);
-                }
+    tree.measure();
+    let program = ast::from_parse_tree(&tree)?;
 
-                Some(23) => {
-                    if let Err(e) = writeln!(
-                        file,
-                        "Rule: Statement⟶if Conditional then Statement else Statement endif"
-                    ) {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.push(ENDIF);
-                    ss.push(STATEMENT);
-                    ss.push(ELSE);
-                    ss.push(STATEMENT);
-                    ss.push(THEN);
-                    ss.push(CONDITIONAL);
-                    ss.push(IF);
-                }
+    Ok(ParseOutput {
+        trace,
+        tree,
+        program,
+    })
+}
 
-                //Handles WHILE statements
-                Some(24) => {
-                    if let Err(e) = writeln!(
-                        file,
-                        "Rule: Statement⟶ while Conditional do Statement whileend "
-                    ) {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(WHILEEND);
-                    ss.push(SEMICOLON);
-                    ss.push(STATEMENT);
-                    ss.push(DO);
-                    ss.push(CONDITIONAL);
-                    ss.push(WHILE);
-                }
+//why a parse failed
+#[derive(Debug)]
+pub struct ParseFailure {
+    pub errors: Vec<CompileError>,
+    pub truncated: bool, // stopped at the error limit, the rest of the input was not checked
+}
 
-                //Handles begin statements
-                Some(25) => {
-                    if let Err(e) =
-                        writeln!(file, "Rule Statement ⟶  begin Statement MoreStatements end")
-                    {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(END);
-                    ss.push(MORESTATEMENTS);
-                    ss.push(STATEMENT);
-                    ss.push(BEGIN);
-                }
+impl From<Vec<CompileError>> for ParseFailure {
+    fn from(errors: Vec<CompileError>) -> ParseFailure {
+        ParseFailure {
+            errors,
+            truncated: false,
+        }
+    }
+}
 
-                //Handles morestatements
-                Some(26) => {
-                    if let Err(e) =
-                        writeln!(file, "Rule: MoreStatements⟶ ; Statement MoreStatements")
-                    {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(MORESTATEMENTS);
-                    ss.push(STATEMENT);
-                    ss.push(SEMICOLON);
-                }
+//the token the parser is looking at, pulled from the rest of the tokens
+struct Cursor<I> {
+    tokens: I,
+    current: Option<TokenType>, // None once the tokens have run out
+    index: usize,               // tokens before the current one
+    last_span: Span,            // the current token, or the last one there was
+}
 
-                //Handle conditionals
-                Some(27) => {
-                    let mut temp_pointer = token_pointer;
-                    let mut relop_check = false;
-                    //Check for a Relop
-                    while temp_pointer < token_list.len() {
-                        match lexer_to_symbol(&token_list[temp_pointer]) {
-                            LTHAN => {
-                                relop_check = true;
-                                break;
-                            }
-
-                            LEQUAL => {
-                                relop_check = true;
-                                break;
-                            }
-
-                            EQUALTO => {
-                                relop_check = true;
-                                break;
-                            }
-
-                            NOTEQUAL => {
-                                relop_check = true;
-                                break;
-                            }
-
-                            GTHANEQUAL => {
-                                relop_check = true;
-                                break;
-                            }
-
-                            GTHAN => {
-                                relop_check = true;
-                                break;
-                            }
-                            THEN => break,
-
-                            _ => temp_pointer = temp_pointer + 1,
-                        }
-                    }
-
-                    if relop_check {
-                        if let Err(e) =
-                            writeln!(file, "Rule:  Conditional⟶ Expression Relop Expression")
-                        {
-                            eprintln!("Could not write to file: {}", e);
-                        }
-                        ss.pop();
-                        ss.push(EXPR);
-                        ss.push(RELOP);
-                        ss.push(EXPR);
-                    } else {
-                        if let Err(e) = writeln!(file, "Rule: Conditional⟶ Expression") {
-                            eprintln!("Could not write to file: {}", e);
-                        }
-                        println!();
-                        ss.pop();
-                        ss.push(EXPR);
-                    }
-                }
+impl<I: Iterator<Item = Result<TokenType>>> Cursor<I> {
+    fn new(tokens: I) -> Result<Cursor<I>> {
+        let mut cursor = Cursor {
+            tokens,
+            current: None,
+            index: 0,
+            last_span: Span::default(),
+        };
+        cursor.pull()?;
+        Ok(cursor)
+    }
 
-                Some(28) => {
-                    if let Err(e) = writeln!(file, "Rule: Relop⟶ <") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(LTHAN);
-                }
+    fn symbol(&self) -> Option<Symbols> {
+        self.current.as_ref().map(lexer_to_symbol)
+    }
 
-                Some(29) => {
-                    if let Err(e) = writeln!(file, "Rule: Relop⟶ <=") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(LEQUAL);
-                }
+    fn advance(&mut self) -> Result<()> {
+        if self.current.is_some() {
+            self.index += 1;
+        }
+        self.pull()
+    }
 
-                Some(30) => {
-                    if let Err(e) = writeln!(file, "Rule: Relop⟶ ==") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(EQUALTO);
-                }
+    fn pull(&mut self) -> Result<()> {
+        self.current = self.tokens.next().transpose()?;
+        if let Some(token) = &self.current {
+            self.last_span = token.span;
+        }
+        Ok(())
+    }
+}
 
-                Some(31) => {
-                    if let Err(e) = writeln!(file, "Rule: Relop⟶ <>") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(NOTEQUAL);
-                }
-                Some(32) => {
-                    if let Err(e) = writeln!(file, "Rule: Relop⟶ >=") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(GTHANEQUAL);
-                }
+//replace the symbol on top of the stack with the right hand side of a rule
+//  the new symbols become the children of the top's parse tree node
+fn expand(ss: &mut Vec<Symbols>, slots: &mut Vec<usize>, tree: &mut ParseTree, rhs: &[Symbols]) {
+    ss.pop();
+    let parent = match slots.pop() {
+        Some(parent) => parent,
+        None => return,
+    };
+
+    let children: Vec<usize> = rhs.iter().map(|symbol| tree.add(*symbol)).collect();
+    for (symbol, child) in rhs.iter().zip(children.iter()).rev() {
+        ss.push(*symbol);
+        slots.push(*child);
+    }
+    tree.nodes[parent].children = children;
+}
 
-                Some(33) => {
-                    if let Err(e) = writeln!(file, "Rule: Relop⟶ >") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.pop();
-                    ss.push(GTHAN);
-                }
+//panic mode recovery: skip tokens until one the parser can continue from, then
+//  pop the stack down to the symbol that accepts it
+fn recover<I: Iterator<Item = Result<TokenType>>>(
+    ss: &mut Vec<Symbols>,
+    slots: &mut Vec<usize>,
+    cursor: &mut Cursor<I>,
+    table: &ParseTable,
+) -> Result<()> {
+    let top = ss[ss.len() - 1];
+
+    //skip to a synchronising token or one the top of the stack can start with
+    let symbol = loop {
+        match cursor.symbol() {
+            Some(symbol) if SYNC_SYMBOLS.contains(&symbol) || accepts(top, symbol, table) => {
+                break symbol
+            }
+            Some(_) => cursor.advance()?,
+            None => return Ok(()),
+        }
+    };
 
-                Some(36) => {
-                    if let Err(e) = writeln!(file, "Rule: MoreIds⟶ , ID MoreIds") {
-                        eprintln!("Could not write to file: {}", e);
-                    }
-                    ss.push(ID_NT);
-                    ss.push(COMMA);
-                    symbol_flag = true;
-                }
+    //pop until a symbol on the stack can handle the token
+    //  the end of stack marker is kept so the loop can finish cleanly
+    while ss.len() > 1 && !accepts(ss[ss.len() - 1], symbol, table) {
+        ss.pop();
+        slots.pop();
+    }
 
-                //default
-                _ => {
-                    println!(
-                        "ERROR! Expected {:?} but got {:?} on line {:?}",
-                        ss[ss.len() - 1],
-                        lexer_to_symbol(&token_list[token_pointer]),
-                        token_list[token_pointer].line
-                    );
-                    break;
-                }
-            }
+    //nothing but the end marker is left, so the rest of the input is extra
+    if ss.len() == 1 {
+        while cursor.symbol().is_some_and(|symbol| symbol != END_OF_STACK) {
+            cursor.advance()?;
         }
     }
+    Ok(())
+}
 
-    // Get output path.
-    // Hacky way to print out the input ui.
-    // Passing a mutable 1 will trigger the second condition.
+//whether the stack symbol can make progress on the token
+fn accepts(top: Symbols, symbol: Symbols, table: &ParseTable) -> bool {
+    if top.is_terminal() {
+        top == symbol
+    } else {
+        table.get(top, symbol).is_some()
+    }
+}
 
-    // TODO Output to file using output path. Maybe have print_symbol_table output straight to the output path.
-    print_symbol_table(ST, output_path);
+//readable names of the terminals that could have come next
+fn expected_symbols(stack: &[Symbols], table: &ParseTable) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for terminal in table.expected(stack) {
+        let name = terminal.describe().to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
 }
 
-fn return_enum_string(temp: Symbols) -> String {
-    match temp {
-        INT => "Integer".to_string(),
-        BOOl => "Bool".to_string(),
-        FLOAT => "Float".to_string(),
-        _ => "Ooops".to_string(),
+//how an unexpected token is shown in an error
+fn describe_token(token: &TokenType) -> String {
+    match lexer_to_symbol(token) {
+        END_OF_STACK => END_OF_STACK.describe().to_string(),
+        _ => format!("`{}`", token.kind),
     }
 }
 
-fn print_symbol_table(ST: Vec<(String, String, usize)>, output_path: String) {
-    let mut table = Table::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer;
+
+    fn failed(source: &str, error_limit: usize) -> ParseFailure {
+        match parse(&lexer(source).unwrap(), error_limit) {
+            Ok(_) => panic!("`{}` should not parse", source),
+            Err(failure) => failure,
+        }
+    }
 
-    //add header
-    table.add_row(row!["TYPE", "Variable", "Line#"]);
+    fn lines(failure: &ParseFailure) -> Vec<usize> {
+        failure
+            .errors
+            .iter()
+            .map(|error| error.location().unwrap().line())
+            .collect()
+    }
 
-    //add data
-    for i in ST {
-        table.add_row(row![i.0, i.1, i.2]);
+    const THREE_ERRORS: &str = "begin int a;\na = 1 +;\na = ;\nb = 2 ) end";
+
+    //recovery carries on past each error, so all of them are reported at once
+    #[test]
+    fn every_error_is_reported() {
+        let failure = failed(THREE_ERRORS, DEFAULT_ERROR_LIMIT);
+        assert_eq!(lines(&failure), vec![2, 3, 4]);
+        assert!(!failure.truncated);
+        assert_eq!(
+            failure.errors[1].message(),
+            "expected `-`, `(`, number, real number, identifier, `not`, `true` or `false`, \
+             found `;`"
+        );
+        //`)` can follow an expression, so the expression is closed before the
+        //  statement list finds nothing to do with it
+        assert_eq!(
+            failure.errors[2].message(),
+            "expected `;` or `end`, found `)`"
+        );
     }
-    let mut output_file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(output_path.trim())
-        .unwrap();
-
-    //print table to stdout
-    match table.print(&mut output_file) {
-        Ok(num) => println!("Successfully printed to {:?}", output_file),
-        Err(err) => println!("{}", err),
+
+    #[test]
+    fn error_limit_stops_the_parse() {
+        let failure = failed(THREE_ERRORS, 2);
+        assert_eq!(lines(&failure), vec![2, 3]);
+        assert!(failure.truncated);
+
+        //0 is no limit at all, even past the default one
+        let source = format!("begin int a;{} a = 1 end", " a = ;".repeat(30));
+        let unlimited = failed(&source, 0);
+        assert_eq!(unlimited.errors.len(), 30);
+        assert!(!unlimited.truncated);
     }
 }
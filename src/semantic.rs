@@ -0,0 +1,522 @@
+//semantic checks on the syntax tree
+//  every name has to be declared once, before it is used, and the types on both
+//  sides of assignments, arithmetic and comparisons have to agree
+//  an int meeting a float in arithmetic or a comparison is promoted to float, and
+//  an int can be stored in a float variable, a float is never narrowed to int
+use crate::ast::{Expr, ExprKind, Ident, Literal, Program, Statement, Type, UnaryOp};
+use crate::error::{CompileError, Location, Note};
+use crate::symbol_table::SymbolTable;
+use crate::tokens::Span;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+//check a parsed program, filling in the symbol table as declarations are met
+//  returns every problem found
+pub fn check(program: &Program, symbols: &mut SymbolTable) -> Vec<CompileError> {
+    let mut checker = Checker {
+        symbols,
+        undeclared: HashMap::new(),
+        loop_variables: Vec::new(),
+        errors: Vec::new(),
+    };
+    for statement in program.statements.iter() {
+        checker.statement(statement);
+    }
+    checker.errors
+}
+
+//what the checker needs to know about a declared name
+#[derive(Copy, Clone)]
+struct Declared {
+    ty: Type,
+    span: Span,
+}
+
+struct Checker<'a> {
+    symbols: &'a mut SymbolTable,
+    undeclared: HashMap<Rc<str>, Vec<usize>>, // errors for names used before any declaration
+    loop_variables: Vec<(Declared, Span)>,    // for loops being checked, with their span
+    errors: Vec<CompileError>,
+}
+
+impl<'a> Checker<'a> {
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Declaration(declaration) => {
+                for name in declaration.names.iter() {
+                    self.declare(name, declaration.ty);
+                }
+            }
+            Statement::Assign(assign) => {
+                let target = self.lookup(&assign.target);
+                self.not_loop_variable(&assign.target, target);
+                let value = self.expr(&assign.value);
+                if let (Some(target), Some(value)) = (target, value) {
+                    if !target.ty.accepts(value) {
+                        self.error(
+                            assign.value.span,
+                            format!(
+                                "mismatched types: cannot assign `{}` to `{}`, which has type `{}`",
+                                value, assign.target.name, target.ty
+                            ),
+                            vec![Note::at(
+                                &format!("`{}` declared here", assign.target.name),
+                                target.span,
+                            )],
+                        );
+                    }
+                }
+            }
+            Statement::If(i) => {
+                self.condition(&i.condition, "if");
+                self.statement(&i.then_branch);
+                if let Some(else_branch) = &i.else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Statement::While(w) => {
+                self.condition(&w.condition, "while");
+                self.statement(&w.body);
+            }
+            Statement::For(f) => {
+                let variable = self.lookup(&f.variable);
+                self.not_loop_variable(&f.variable, variable);
+                if let Some(variable) = variable {
+                    self.integer(
+                        &format!("loop variable `{}`", f.variable.name),
+                        f.variable.span,
+                        variable.ty,
+                    );
+                }
+                for bound in [&f.start, &f.end] {
+                    if let Some(ty) = self.expr(bound) {
+                        self.integer("loop bound", bound.span, ty);
+                    }
+                }
+
+                if let Some(variable) = variable {
+                    self.loop_variables.push((variable, f.variable.span));
+                }
+                self.statement(&f.body);
+                if variable.is_some() {
+                    self.loop_variables.pop();
+                }
+            }
+            Statement::DoWhile(d) => {
+                self.statement(&d.body);
+                self.condition(&d.condition, "do");
+            }
+            Statement::Block(b) => {
+                self.symbols.enter_scope();
+                for statement in b.statements.iter() {
+                    self.statement(statement);
+                }
+                self.symbols.exit_scope();
+            }
+            Statement::Input(input) => {
+                for target in input.targets.iter() {
+                    let declared = self.lookup(target);
+                    self.not_loop_variable(target, declared);
+                    if let Some(declared) = declared {
+                        if !declared.ty.is_numeric() {
+                            self.error(
+                                target.span,
+                                format!(
+                                    "`input` reads numbers, `{}` has type `{}`",
+                                    target.name, declared.ty
+                                ),
+                                vec![Note::at(
+                                    &format!("`{}` declared here", target.name),
+                                    declared.span,
+                                )],
+                            );
+                        }
+                    }
+                }
+            }
+            Statement::Output(output) => {
+                for value in output.values.iter() {
+                    self.expr(value);
+                }
+            }
+            Statement::Expr(e) => {
+                self.expr(e);
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Ident, ty: Type) {
+        if let Err(e) = self.symbols.insert(name, ty) {
+            self.errors.push(e);
+            return;
+        }
+
+        //point the earlier uses at the declaration that came too late
+        if let Some(uses) = self.undeclared.remove(&name.name) {
+            for index in uses {
+                if let CompileError::Semantic { notes, .. } = &mut self.errors[index] {
+                    notes.push(Note::at(
+                        &format!("`{}` is declared here, after its first use", name.name),
+                        name.span,
+                    ));
+                }
+            }
+        }
+    }
+
+    //None when the name is not declared yet, after reporting it
+    fn lookup(&mut self, name: &Ident) -> Option<Declared> {
+        if let Some(symbol) = self.symbols.reference(name) {
+            return Some(Declared {
+                ty: symbol.ty,
+                span: symbol.span,
+            });
+        }
+
+        self.undeclared
+            .entry(Rc::clone(&name.name))
+            .or_default()
+            .push(self.errors.len());
+        self.error(
+            name.span,
+            format!("use of undeclared variable `{}`", name.name),
+            Vec::new(),
+        );
+        None
+    }
+
+    //for loops count with an int and count between int bounds
+    fn integer(&mut self, what: &str, span: Span, ty: Type) {
+        if ty != Type::Int {
+            self.error(
+                span,
+                format!("{} has type `{}`, expected `int`", what, ty),
+                Vec::new(),
+            );
+        }
+    }
+
+    //the body of a for loop must not change the variable it counts with
+    fn not_loop_variable(&mut self, name: &Ident, declared: Option<Declared>) {
+        let declared = match declared {
+            Some(declared) => declared,
+            None => return,
+        };
+        let active = self
+            .loop_variables
+            .iter()
+            .find(|(variable, _)| variable.span == declared.span);
+        if let Some((_, loop_span)) = active {
+            let note = Note::at(&format!("`{}` counts this for loop", name.name), *loop_span);
+            self.error(
+                name.span,
+                format!(
+                    "cannot change loop variable `{}` inside its loop",
+                    name.name
+                ),
+                vec![note],
+            );
+        }
+    }
+
+    fn condition(&mut self, condition: &Expr, keyword: &str) {
+        if let Some(ty) = self.expr(condition) {
+            if ty != Type::Bool {
+                self.error(
+                    condition.span,
+                    format!("`{}` condition has type `{}`, expected `bool`", keyword, ty),
+                    Vec::new(),
+                );
+            }
+        }
+    }
+
+    //type of an expression, None when it already produced an error
+    fn expr(&mut self, expr: &Expr) -> Option<Type> {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Int(_)) => Some(Type::Int),
+            ExprKind::Literal(Literal::Float(_)) => Some(Type::Float),
+            ExprKind::Literal(Literal::Bool(_)) => Some(Type::Bool),
+            ExprKind::Var(ident) => self.lookup(ident).map(|declared| declared.ty),
+            ExprKind::Binary { op, lhs, rhs } => {
+                let (lhs_ty, rhs_ty) = (self.expr(lhs), self.expr(rhs));
+                let (lhs_ty, rhs_ty) = (lhs_ty?, rhs_ty?);
+                for (side, ty) in [(lhs, lhs_ty), (rhs, rhs_ty)] {
+                    if !ty.is_numeric() {
+                        self.error(
+                            side.span,
+                            format!("`{}` cannot be applied to `{}`", op, ty),
+                            Vec::new(),
+                        );
+                        return None;
+                    }
+                }
+                Some(lhs_ty.promote(rhs_ty))
+            }
+            ExprKind::Relop { op, lhs, rhs } => {
+                let (lhs_ty, rhs_ty) = (self.expr(lhs), self.expr(rhs));
+                let (lhs_ty, rhs_ty) = (lhs_ty?, rhs_ty?);
+                let comparable = lhs_ty == rhs_ty || (lhs_ty.is_numeric() && rhs_ty.is_numeric());
+                if !comparable {
+                    self.error(
+                        expr.span,
+                        format!("cannot compare `{}` with `{}`", lhs_ty, rhs_ty),
+                        Vec::new(),
+                    );
+                    return None;
+                }
+                if op.is_ordering() && !lhs_ty.is_numeric() {
+                    self.error(
+                        expr.span,
+                        format!("`{}` cannot be applied to `{}`", op, lhs_ty),
+                        Vec::new(),
+                    );
+                    return None;
+                }
+                Some(Type::Bool)
+            }
+            ExprKind::Logical { op, lhs, rhs } => {
+                let (lhs_ty, rhs_ty) = (self.expr(lhs), self.expr(rhs));
+                let (lhs_ty, rhs_ty) = (lhs_ty?, rhs_ty?);
+                self.boolean(&op.to_string(), lhs, lhs_ty)?;
+                self.boolean(&op.to_string(), rhs, rhs_ty)?;
+                Some(Type::Bool)
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => {
+                let ty = self.expr(operand)?;
+                self.boolean("not", operand, ty)?;
+                Some(Type::Bool)
+            }
+            ExprKind::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => {
+                let ty = self.expr(operand)?;
+                if !ty.is_numeric() {
+                    self.error(
+                        operand.span,
+                        format!("`-` cannot be applied to `{}`", ty),
+                        Vec::new(),
+                    );
+                    return None;
+                }
+                Some(ty)
+            }
+        }
+    }
+
+    //operands of and, or and not have to be bool
+    fn boolean(&mut self, op: &str, operand: &Expr, ty: Type) -> Option<()> {
+        if ty == Type::Bool {
+            return Some(());
+        }
+        self.error(
+            operand.span,
+            format!("`{}` needs `bool` operands, found `{}`", op, ty),
+            Vec::new(),
+        );
+        None
+    }
+
+    fn error(&mut self, span: Span, message: String, notes: Vec<Note>) {
+        self.errors.push(CompileError::Semantic {
+            location: Location::from(span),
+            message,
+            notes,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer;
+    use crate::parser::parse;
+
+    //an error's message and the text it points at, then the same for its notes
+    //  the text is followed by the byte offset it starts at, like `a@6`
+    type Reported = (String, String, Vec<(String, String)>);
+
+    fn errors(source: &str) -> Vec<Reported> {
+        let program = match parse(&lexer(source).unwrap(), 0) {
+            Ok(output) => output.program,
+            Err(failure) => panic!("`{}` should parse: {:?}", source, failure.errors),
+        };
+        let text = |span: Span| {
+            let text = &source[span.start.offset..span.end.offset];
+            format!("{}@{}", text, span.start.offset)
+        };
+        check(&program, &mut SymbolTable::new(0))
+            .iter()
+            .map(|error| {
+                let notes = error
+                    .notes()
+                    .iter()
+                    .map(|note| (note.message.clone(), text(note.span.unwrap())))
+                    .collect();
+                (error.message(), text(error.location().unwrap().span), notes)
+            })
+            .collect()
+    }
+
+    fn error(message: &str, at: &str, notes: &[(&str, &str)]) -> Reported {
+        let notes = notes
+            .iter()
+            .map(|(message, at)| (message.to_string(), at.to_string()))
+            .collect();
+        (message.to_string(), at.to_string(), notes)
+    }
+
+    //each use before the declaration is an error, and points at the declaration
+    //  once there is one
+    #[test]
+    fn use_before_declaration() {
+        let late = "`a` is declared here, after its first use";
+        assert_eq!(
+            errors("begin a = 1; b = a; int a end"),
+            vec![
+                error("use of undeclared variable `a`", "a@6", &[(late, "a@24")]),
+                error("use of undeclared variable `b`", "b@13", &[]),
+                error("use of undeclared variable `a`", "a@17", &[(late, "a@24")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn declared_twice() {
+        assert_eq!(
+            errors("begin int a, a; a = 1 end"),
+            vec![error(
+                "`a` is declared more than once",
+                "a@13",
+                &[("`a` first declared here", "a@10")]
+            )]
+        );
+    }
+
+    //an int can go into a float, nothing else crosses types
+    #[test]
+    fn assignment_types() {
+        let source = "begin int a; bool b; float f; a = true; a = 1.5; f = b; b = 1; f = 1 end";
+        assert_eq!(
+            errors(source),
+            vec![
+                error(
+                    "mismatched types: cannot assign `bool` to `a`, which has type `int`",
+                    "true@34",
+                    &[("`a` declared here", "a@10")]
+                ),
+                error(
+                    "mismatched types: cannot assign `float` to `a`, which has type `int`",
+                    "1.5@44",
+                    &[("`a` declared here", "a@10")]
+                ),
+                error(
+                    "mismatched types: cannot assign `bool` to `f`, which has type `float`",
+                    "b@53",
+                    &[("`f` declared here", "f@27")]
+                ),
+                error(
+                    "mismatched types: cannot assign `int` to `b`, which has type `bool`",
+                    "1@60",
+                    &[("`b` declared here", "b@18")]
+                ),
+            ]
+        );
+    }
+
+    //int and float mix, promoting to float, bool does not mix with either
+    #[test]
+    fn arithmetic_types() {
+        let source = "begin int a; bool b; float f; \
+                      a = 1 + b; a = b * 2; f = f - 1; a = a + f; b = 1 < b; b = -b end";
+        assert_eq!(
+            errors(source),
+            vec![
+                error("`+` cannot be applied to `bool`", "b@38", &[]),
+                error("`*` cannot be applied to `bool`", "b@45", &[]),
+                error(
+                    "mismatched types: cannot assign `float` to `a`, which has type `int`",
+                    "a + f@67",
+                    &[("`a` declared here", "a@10")]
+                ),
+                error("cannot compare `int` with `bool`", "1 < b@78", &[]),
+                error("`-` cannot be applied to `bool`", "b@90", &[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn conditions_are_bool() {
+        let source = "begin int a; bool b; if a then b = true endif; \
+                      while 1.5 do b = false whileend; do a = 1 doend while a + 1 end";
+        assert_eq!(
+            errors(source),
+            vec![
+                error(
+                    "`if` condition has type `int`, expected `bool`",
+                    "a@24",
+                    &[]
+                ),
+                error(
+                    "`while` condition has type `float`, expected `bool`",
+                    "1.5@53",
+                    &[]
+                ),
+                error(
+                    "`do` condition has type `int`, expected `bool`",
+                    "a + 1@101",
+                    &[]
+                ),
+            ]
+        );
+    }
+
+    //the loop body cannot assign, read into or count with its own variable, and a
+    //  loop counts with an int between int bounds
+    #[test]
+    fn for_loops() {
+        let source = "begin int i; float f; \
+                      for i = 1, 3 do i = 2; input(i); for i = 1, 2 do output(i) forend forend; \
+                      for f = 1, 2.5 do output(f) forend; for i = true, 1 do output(i) forend end";
+        let counts = ("`i` counts this for loop", "i@26");
+        assert_eq!(
+            errors(source),
+            vec![
+                error(
+                    "cannot change loop variable `i` inside its loop",
+                    "i@38",
+                    &[counts]
+                ),
+                error(
+                    "cannot change loop variable `i` inside its loop",
+                    "i@51",
+                    &[counts]
+                ),
+                error(
+                    "cannot change loop variable `i` inside its loop",
+                    "i@59",
+                    &[counts]
+                ),
+                error(
+                    "loop variable `f` has type `float`, expected `int`",
+                    "f@100",
+                    &[]
+                ),
+                error(
+                    "loop bound has type `float`, expected `int`",
+                    "2.5@107",
+                    &[]
+                ),
+                error(
+                    "loop bound has type `bool`, expected `int`",
+                    "true@140",
+                    &[]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//symbol table filled in by the semantic pass
+//  every declared variable gets a memory address, counting up from a base
+//  address, and remembers where it is used
+//  `begin ... end` blocks open a new scope, a name can be declared once per scope
+use crate::ast::{Ident, Type};
+use crate::error::{CompileError, Location, Note};
+use crate::tokens::Span;
+use prettytable::Table;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::Write as IoWrite;
+use std::rc::Rc;
+
+//address given to the first variable when none is configured
+pub const DEFAULT_BASE_ADDRESS: usize = 5000;
+
+//one declared variable
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub name: Rc<str>,
+    pub ty: Type,
+    pub span: Span, // the name in its declaration
+    pub address: usize,
+    pub scope: usize, // nesting depth of the declaring scope, 0 is outermost
+    pub references: Vec<Span>, // every later use of the name
+}
+
+impl Symbol {
+    pub fn line(&self) -> usize {
+        self.span.start.line
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,                 // in declaration order
+    scopes: Vec<HashMap<Rc<str>, usize>>, // open scopes, innermost last, names map into symbols
+    resolved: HashMap<Span, usize>,       // symbol each declared or used name refers to
+    base_address: usize,
+}
+
+impl SymbolTable {
+    pub fn new(base_address: usize) -> SymbolTable {
+        SymbolTable {
+            symbols: Vec::new(),
+            scopes: vec![HashMap::new()],
+            resolved: HashMap::new(),
+            base_address,
+        }
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    //names declared in the scope stop being visible, their entries are kept
+    pub fn exit_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    //declare a name in the innermost scope and give it the next free address
+    //  fails when the scope already declares the name
+    pub fn insert(&mut self, name: &Ident, ty: Type) -> Result<&Symbol, CompileError> {
+        let depth = self.scopes.len() - 1;
+        if let Some(index) = self.scopes[depth].get(&name.name) {
+            let first = &self.symbols[*index];
+            return Err(CompileError::Semantic {
+                location: Location::from(name.span),
+                message: format!("`{}` is declared more than once", name.name),
+                notes: vec![Note::at(
+                    &format!("`{}` first declared here", name.name),
+                    first.span,
+                )],
+            });
+        }
+
+        let index = self.symbols.len();
+        self.symbols.push(Symbol {
+            name: Rc::clone(&name.name),
+            ty,
+            span: name.span,
+            address: self.base_address + index,
+            scope: depth,
+            references: Vec::new(),
+        });
+        self.scopes[depth].insert(Rc::clone(&name.name), index);
+        self.resolved.insert(name.span, index);
+        Ok(&self.symbols[index])
+    }
+
+    //the visible declaration of a name, searching outwards from the innermost scope
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .map(|index| &self.symbols[*index])
+    }
+
+    //look a name up and record the use
+    pub fn reference(&mut self, name: &Ident) -> Option<&Symbol> {
+        let index = *self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.name))?;
+        self.symbols[index].references.push(name.span);
+        self.resolved.insert(name.span, index);
+        Some(&self.symbols[index])
+    }
+
+    //the symbol a name in the program was bound to by insert() or reference()
+    //  works after the scopes have been closed, later passes use it to find addresses
+    //  names are matched by their span, so the table must come from the semantic
+    //  pass over the same program
+    pub fn resolve(&self, name: &Ident) -> Option<&Symbol> {
+        self.resolved
+            .get(&name.span)
+            .map(|index| &self.symbols[*index])
+    }
+
+    //address of the first symbol
+    pub fn base_address(&self) -> usize {
+        self.base_address
+    }
+
+    //every symbol ever declared, including those in closed scopes
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    //print the symbol table to the given output as a pretty table
+    pub fn print_table(&self, output_file: &mut dyn IoWrite) -> io::Result<()> {
+        let mut table = Table::new();
+
+        //add header
+        table.add_row(row![
+            "TYPE",
+            "Variable",
+            "Memory Address",
+            "Line#",
+            "Used on"
+        ]);
+
+        //add data
+        for symbol in self.symbols.iter() {
+            table.add_row(row![
+                type_name(symbol.ty),
+                symbol.name,
+                symbol.address,
+                symbol.line(),
+                used_on(symbol)
+            ]);
+        }
+
+        //print table to the output
+        table.print(output_file)?;
+        Ok(())
+    }
+}
+
+impl Default for SymbolTable {
+    fn default() -> SymbolTable {
+        SymbolTable::new(DEFAULT_BASE_ADDRESS)
+    }
+}
+
+//one line per symbol, for logs and quick looks
+//  5000 int a (line 3, used on 4, 7)
+impl fmt::Display for SymbolTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for symbol in self.symbols.iter() {
+            write!(
+                f,
+                "{} {} {} (line {}",
+                symbol.address,
+                symbol.ty,
+                symbol.name,
+                symbol.line()
+            )?;
+            if !symbol.references.is_empty() {
+                write!(f, ", used on {}", used_on(symbol))?;
+            }
+            writeln!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+fn type_name(ty: Type) -> &'static str {
+    match ty {
+        Type::Int => "Integer",
+        Type::Bool => "Bool",
+        Type::Float => "Float",
+    }
+}
+
+//lines a symbol is used on, without repeats
+fn used_on(symbol: &Symbol) -> String {
+    let mut lines: Vec<usize> = symbol.references.iter().map(|s| s.start.line).collect();
+    lines.dedup();
+    lines
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokens::Position;
+
+    //a name at its own offset, so every use has a span of its own
+    fn ident(name: &str, offset: usize) -> Ident {
+        let start = Position {
+            offset,
+            line: 1,
+            column: offset + 1,
+        };
+        let end = Position {
+            offset: offset + name.len(),
+            line: 1,
+            column: offset + name.len() + 1,
+        };
+        Ident {
+            name: name.into(),
+            span: Span::new(start, end),
+        }
+    }
+
+    #[test]
+    fn scopes() {
+        let mut table = SymbolTable::new(DEFAULT_BASE_ADDRESS);
+        table.insert(&ident("a", 0), Type::Int).unwrap();
+        table.enter_scope();
+        table.insert(&ident("b", 10), Type::Bool).unwrap();
+        assert_eq!(table.lookup("a").unwrap().scope, 0);
+        assert_eq!(table.lookup("b").unwrap().scope, 1);
+
+        table.exit_scope();
+        assert!(table.lookup("b").is_none());
+        //the closed scope's names keep their entries and addresses
+        assert_eq!(table.len(), 2);
+        assert_eq!(&*table.symbols()[1].name, "b");
+
+        //the outermost scope is never closed
+        table.exit_scope();
+        assert!(table.lookup("a").is_some());
+    }
+
+    //an inner declaration hides the outer one until its scope closes
+    #[test]
+    fn shadowing() {
+        let mut table = SymbolTable::new(DEFAULT_BASE_ADDRESS);
+        table.insert(&ident("a", 0), Type::Int).unwrap();
+        table.enter_scope();
+        table.insert(&ident("a", 10), Type::Float).unwrap();
+        let inner = ident("a", 20);
+        assert_eq!(table.reference(&inner).unwrap().ty, Type::Float);
+        table.exit_scope();
+        let outer = ident("a", 30);
+        assert_eq!(table.reference(&outer).unwrap().ty, Type::Int);
+
+        //each use stays bound to the declaration it saw
+        assert_eq!(table.resolve(&inner).unwrap().address, 5001);
+        assert_eq!(table.resolve(&outer).unwrap().address, 5000);
+    }
+
+    #[test]
+    fn declared_twice() {
+        let mut table = SymbolTable::new(DEFAULT_BASE_ADDRESS);
+        let first = ident("a", 4);
+        let second = ident("a", 7);
+        table.insert(&first, Type::Int).unwrap();
+        let error = table.insert(&second, Type::Float).unwrap_err();
+        assert_eq!(error.message(), "`a` is declared more than once");
+        assert_eq!(error.location().unwrap().span, second.span);
+        assert_eq!(error.notes().len(), 1);
+        assert_eq!(error.notes()[0].message, "`a` first declared here");
+        assert_eq!(error.notes()[0].span, Some(first.span));
+
+        //the first declaration is kept as it was
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.lookup("a").unwrap().ty, Type::Int);
+    }
+
+    //addresses count up from the base in declaration order, across scopes
+    #[test]
+    fn addresses() {
+        let mut table = SymbolTable::new(100);
+        table.insert(&ident("a", 0), Type::Int).unwrap();
+        table.enter_scope();
+        table.insert(&ident("b", 10), Type::Int).unwrap();
+        table.exit_scope();
+        table.insert(&ident("c", 20), Type::Int).unwrap();
+
+        let addresses: Vec<usize> = table.symbols().iter().map(|s| s.address).collect();
+        assert_eq!(addresses, vec![100, 101, 102]);
+        assert_eq!(table.base_address(), 100);
+    }
+
+    //reference() records each use, lookup() and failed references record nothing
+    #[test]
+    fn references() {
+        let mut table = SymbolTable::new(DEFAULT_BASE_ADDRESS);
+        let declared = ident("a", 0);
+        table.insert(&declared, Type::Int).unwrap();
+        let uses = [ident("a", 10), ident("a", 20)];
+        for name in uses.iter() {
+            table.reference(name).unwrap();
+        }
+        table.lookup("a").unwrap();
+        assert!(table.reference(&ident("b", 30)).is_none());
+
+        let spans: Vec<Span> = uses.iter().map(|name| name.span).collect();
+        assert_eq!(table.symbols()[0].references, spans);
+        assert!(table.resolve(&declared).is_some());
+        assert!(table.resolve(&ident("b", 30)).is_none());
+    }
+}
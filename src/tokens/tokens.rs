@@ -1,26 +1,338 @@
- #[path = "../lexer/fsm.rs"]
- pub mod fsm;
- pub use fsm::FsmTransitions;
- pub use fsm::FsmTransitions::*;
+use std::fmt;
+use std::rc::Rc;
 
- //implement deep copy
+//a point in the source text
+//  line and column start at 1, column counts characters so a tab is one column
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub offset: usize, // byte offset from the start of the source
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    //move past one character
+    //  "\r\n" counts as a single line break and a lone '\r' takes no space
+    pub fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+        match c {
+            '\n' => {
+                self.line += 1;
+                self.column = 1;
+            }
+            '\r' => {}
+            _ => self.column += 1,
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+//the source text covered by a token, end is one past the last character
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Span {
+        Span { start, end }
+    }
+
+    //smallest span covering both
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+//what a token is, each keyword, operator and separator has its own variant and
+//  literals carry their value
+//  identifier names are interned by the lexer, so every use of a name shares one
+//  allocation
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    //keywords
+    Int,
+    Float,
+    Bool,
+    True,
+    False,
+    If,
+    Else,
+    Then,
+    Endif,
+    While,
+    Whileend,
+    Do,
+    Doend,
+    For,
+    Forend,
+    Input,
+    Output,
+    And,
+    Or,
+    Not,
+    Begin,
+    End,
+
+    //operators
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Assign,
+    EqualEqual,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+
+    //separators
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Comma,
+    Colon,
+    Semicolon,
+    Quote,
+
+    //literals, an integer has no sign since `-` is always its own token
+    Integer(u64),
+    Real(f64),
+    Identifier(Rc<str>),
+
+    //the token after the last one, it covers no text
+    Eof,
+}
+
+use TokenKind::*;
+
+impl TokenKind {
+    pub fn keyword(word: &str) -> Option<TokenKind> {
+        let kind = match word {
+            "int" => Int,
+            "float" => Float,
+            "bool" => Bool,
+            "true" => True,
+            "false" => False,
+            "if" => If,
+            "else" => Else,
+            "then" => Then,
+            "endif" => Endif,
+            "while" => While,
+            "whileend" => Whileend,
+            "do" => Do,
+            "doend" => Doend,
+            "for" => For,
+            "forend" => Forend,
+            "input" => Input,
+            "output" => Output,
+            "and" => And,
+            "or" => Or,
+            "not" => Not,
+            "begin" => Begin,
+            "end" => End,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    pub fn operator(text: &str) -> Option<TokenKind> {
+        let kind = match text {
+            "+" => Plus,
+            "-" => Minus,
+            "*" => Star,
+            "/" => Slash,
+            "%" => Percent,
+            "=" => Assign,
+            "==" => EqualEqual,
+            "<>" => NotEqual,
+            "<" => Less,
+            "<=" => LessEqual,
+            ">" => Greater,
+            ">=" => GreaterEqual,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    pub fn separator(c: char) -> Option<TokenKind> {
+        let kind = match c {
+            '(' => LParen,
+            ')' => RParen,
+            '{' => LBrace,
+            '}' => RBrace,
+            '[' => LBracket,
+            ']' => RBracket,
+            ',' => Comma,
+            ':' => Colon,
+            ';' => Semicolon,
+            '\'' => Quote,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    //name of the token's class in the token listing
+    pub fn class(&self) -> &'static str {
+        match self {
+            Int | Float | Bool | True | False | If | Else | Then | Endif | While | Whileend
+            | Do | Doend | For | Forend | Input | Output | And | Or | Not | Begin | End => {
+                "KEYWORD"
+            }
+            Plus | Minus | Star | Slash | Percent | Assign | EqualEqual | NotEqual | Less
+            | LessEqual | Greater | GreaterEqual => "OPERATOR",
+            LParen | RParen | LBrace | RBrace | LBracket | RBracket | Comma | Colon | Semicolon
+            | Quote => "SEPARATOR",
+            Integer(_) => "INTEGER",
+            Real(_) => "REAL",
+            Identifier(_) => "IDENTIFIER",
+            Eof => "EOF",
+        }
+    }
+}
+
+//the token as it would be written in source, numbers as their plain decimal value
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Integer(value) => return write!(f, "{}", value),
+            //whole numbers keep a dot so they still read as real
+            Real(value) if value.fract() == 0.0 => return write!(f, "{:.1}", value),
+            Real(value) => return write!(f, "{}", value),
+            Identifier(name) => return f.write_str(name),
+            Int => "int",
+            Float => "float",
+            Bool => "bool",
+            True => "true",
+            False => "false",
+            If => "if",
+            Else => "else",
+            Then => "then",
+            Endif => "endif",
+            While => "while",
+            Whileend => "whileend",
+            Do => "do",
+            Doend => "doend",
+            For => "for",
+            Forend => "forend",
+            Input => "input",
+            Output => "output",
+            And => "and",
+            Or => "or",
+            Not => "not",
+            Begin => "begin",
+            End => "end",
+            Plus => "+",
+            Minus => "-",
+            Star => "*",
+            Slash => "/",
+            Percent => "%",
+            Assign => "=",
+            EqualEqual => "==",
+            NotEqual => "<>",
+            Less => "<",
+            LessEqual => "<=",
+            Greater => ">",
+            GreaterEqual => ">=",
+            LParen => "(",
+            RParen => ")",
+            LBrace => "{",
+            RBrace => "}",
+            LBracket => "[",
+            RBracket => "]",
+            Comma => ",",
+            Colon => ":",
+            Semicolon => ";",
+            Quote => "'",
+            Eof => "",
+        };
+        f.write_str(text)
+    }
+}
+
+//implement deep copy
 #[derive(Clone, Debug)]
 pub struct TokenType {
-    pub token: String,
-    pub lexeme: fsm::FsmTransitions,
-    pub lexeme_name: String,
-    pub line: usize,
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl TokenType {
+    //line the token starts on
+    pub fn line(&self) -> usize {
+        self.span.start.line
+    }
 }
 
 //default state
 impl Default for TokenType {
     fn default() -> TokenType {
         TokenType {
-            token: "".to_string(),
-            lexeme: fsm::_Reject,
-            lexeme_name: "".to_string(),
-            line: 0,
+            kind: Eof,
+            span: Span::default(),
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(offset: usize, line: usize, column: usize) -> Position {
+        Position {
+            offset,
+            line,
+            column,
+        }
+    }
+
+    fn walk(text: &str) -> Vec<Position> {
+        let mut position = Position::default();
+        text.chars()
+            .map(|c| {
+                position.advance(c);
+                position
+            })
+            .collect()
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(walk("a\nb"), vec![at(1, 1, 2), at(2, 2, 1), at(3, 2, 2)]);
+        //the '\r' of "\r\n" takes no column, so only one line break is counted
+        assert_eq!(
+            walk("a\r\nb"),
+            vec![at(1, 1, 2), at(2, 1, 2), at(3, 2, 1), at(4, 2, 2)]
+        );
+    }
+
+    #[test]
+    fn tab_is_one_column() {
+        assert_eq!(walk("\t\ta"), vec![at(1, 1, 2), at(2, 1, 3), at(3, 1, 4)]);
+    }
+
+    //the offset counts bytes and the column counts characters
+    #[test]
+    fn offset_and_column() {
+        assert_eq!(walk("üa"), vec![at(2, 1, 2), at(3, 1, 3)]);
+        assert_eq!(walk("€\n"), vec![at(3, 1, 2), at(4, 2, 1)]);
+    }
+}
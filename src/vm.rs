@@ -0,0 +1,301 @@
+//virtual machine for the generated stack machine code
+//  an operand stack, one memory cell per symbol table address and a program
+//  counter holding the number of the next instruction, counting from 1
+//  jumping to one past the last instruction ends the program
+//  values are ints or floats, arithmetic and comparisons on an int and a float
+//  promote the int, float variables turn the ints stored in them into floats
+use crate::ast::Type;
+use crate::codegen::Instruction;
+use crate::error::{CompileError, Result};
+use crate::symbol_table::SymbolTable;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, Write as IoWrite};
+
+//run a program to the end
+//  STDIN reads whitespace separated numbers from input, STDOUT prints one value per line
+pub fn run(
+    code: &[Instruction],
+    symbols: &SymbolTable,
+    input: &mut dyn BufRead,
+    output: &mut dyn IoWrite,
+) -> Result<()> {
+    let cells = symbols
+        .symbols()
+        .iter()
+        .map(|symbol| match symbol.ty {
+            Type::Float => Value::Float(0.0),
+            Type::Int | Type::Bool => Value::Int(0),
+        })
+        .collect();
+    let mut machine = Machine::new(code, symbols.base_address(), cells);
+    machine.run(input, output)
+}
+
+//one stack entry or memory cell, bools are the ints 0 and 1
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+}
+
+impl Value {
+    pub(crate) fn as_float(self) -> f64 {
+        match self {
+            Value::Int(value) => value as f64,
+            Value::Float(value) => value,
+        }
+    }
+
+    pub(crate) fn is_zero(self) -> bool {
+        match self {
+            Value::Int(value) => value == 0,
+            Value::Float(value) => value == 0.0,
+        }
+    }
+
+    //self op other, using the float op when either is a float
+    //  None when the int op overflowed
+    pub(crate) fn arithmetic(
+        self,
+        other: Value,
+        int: fn(i64, i64) -> Option<i64>,
+        float: fn(f64, f64) -> f64,
+    ) -> Option<Value> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => int(a, b).map(Value::Int),
+            _ => Some(Value::Float(float(self.as_float(), other.as_float()))),
+        }
+    }
+
+    //whether the comparison holds, on floats when either is a float
+    pub(crate) fn compare(
+        self,
+        other: Value,
+        int: fn(i64, i64) -> bool,
+        float: fn(f64, f64) -> bool,
+    ) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => int(a, b),
+            _ => float(self.as_float(), other.as_float()),
+        }
+    }
+
+    //a word of input, words that are not integers are read as floats
+    pub(crate) fn parse(word: &str) -> Option<Value> {
+        if let Ok(value) = word.parse::<i64>() {
+            return Some(Value::Int(value));
+        }
+        match word.parse::<f64>() {
+            Ok(value) if value.is_finite() => Some(Value::Float(value)),
+            _ => None,
+        }
+    }
+}
+
+//whitespace separated words of a program's input, read a line at a time
+#[derive(Debug, Default)]
+pub(crate) struct Words {
+    pending: VecDeque<String>, // words read but not used yet
+}
+
+impl Words {
+    //None once the input has ended
+    pub(crate) fn next(&mut self, input: &mut dyn BufRead) -> Result<Option<String>> {
+        while self.pending.is_empty() {
+            let mut line = String::new();
+            let read = input
+                .read_line(&mut line)
+                .map_err(|e| CompileError::io("<input>", e))?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.pending
+                .extend(line.split_whitespace().map(|word| word.to_string()));
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+//floats always show a fraction so 2.0 and 2 print differently
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+pub struct Machine<'a> {
+    code: &'a [Instruction],
+    stack: Vec<Value>,
+    memory: Vec<Value>,
+    base_address: usize,
+    pc: usize,
+    words: Words,
+}
+
+impl<'a> Machine<'a> {
+    //memory holds `cells` from `base_address` up, each starting as the zero of its type
+    pub fn new(code: &'a [Instruction], base_address: usize, cells: Vec<Value>) -> Machine<'a> {
+        Machine {
+            code,
+            stack: Vec::new(),
+            memory: cells,
+            base_address,
+            pc: 1,
+            words: Words::default(),
+        }
+    }
+
+    pub fn run(&mut self, input: &mut dyn BufRead, output: &mut dyn IoWrite) -> Result<()> {
+        while self.pc <= self.code.len() {
+            self.step(input, output)?;
+        }
+        output.flush().map_err(|e| CompileError::io("<output>", e))
+    }
+
+    //value stored at an address, None outside of memory
+    pub fn load(&self, address: usize) -> Option<Value> {
+        let cell = address.checked_sub(self.base_address)?;
+        self.memory.get(cell).copied()
+    }
+
+    //carry out the instruction at pc
+    fn step(&mut self, input: &mut dyn BufRead, output: &mut dyn IoWrite) -> Result<()> {
+        let instruction = self.code[self.pc - 1];
+        let mut next = self.pc + 1;
+
+        match instruction {
+            Instruction::Pushi(value) => self.stack.push(Value::Int(value)),
+            Instruction::Pushf(value) => self.stack.push(Value::Float(value)),
+            Instruction::Pushm(address) => {
+                let cell = self.cell(address)?;
+                self.stack.push(self.memory[cell]);
+            }
+            Instruction::Popm(address) => {
+                let cell = self.cell(address)?;
+                let value = self.pop()?;
+                self.memory[cell] = self.store(self.memory[cell], value)?;
+            }
+            Instruction::Stdout => {
+                let value = self.pop()?;
+                writeln!(output, "{}", value).map_err(|e| CompileError::io("<output>", e))?;
+            }
+            Instruction::Stdin => {
+                let value = self.read(input)?;
+                self.stack.push(value);
+            }
+            Instruction::Add => self.arithmetic(i64::checked_add, |a, b| a + b)?,
+            Instruction::Sub => self.arithmetic(i64::checked_sub, |a, b| a - b)?,
+            Instruction::Mul => self.arithmetic(i64::checked_mul, |a, b| a * b)?,
+            Instruction::Div => {
+                if self.stack.last().is_some_and(|value| value.is_zero()) {
+                    return Err(self.error("division by zero".to_string()));
+                }
+                self.arithmetic(i64::checked_div, |a, b| a / b)?
+            }
+            Instruction::Grt => self.compare(|a, b| a > b, |a, b| a > b)?,
+            Instruction::Les => self.compare(|a, b| a < b, |a, b| a < b)?,
+            Instruction::Equ => self.compare(|a, b| a == b, |a, b| a == b)?,
+            Instruction::Neq => self.compare(|a, b| a != b, |a, b| a != b)?,
+            Instruction::Geq => self.compare(|a, b| a >= b, |a, b| a >= b)?,
+            Instruction::Leq => self.compare(|a, b| a <= b, |a, b| a <= b)?,
+            Instruction::Jumpz(target) => {
+                if self.pop()?.is_zero() {
+                    next = self.target(target)?;
+                }
+            }
+            Instruction::Jump(target) => next = self.target(target)?,
+            Instruction::Label => {}
+        }
+
+        self.pc = next;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value> {
+        match self.stack.pop() {
+            Some(value) => Ok(value),
+            None => Err(self.error("stack underflow".to_string())),
+        }
+    }
+
+    //pop b then a and push op(a, b)
+    fn arithmetic(
+        &mut self,
+        int: fn(i64, i64) -> Option<i64>,
+        float: fn(f64, f64) -> f64,
+    ) -> Result<()> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        match a.arithmetic(b, int, float) {
+            Some(value) => {
+                self.stack.push(value);
+                Ok(())
+            }
+            None => Err(self.error("integer overflow".to_string())),
+        }
+    }
+
+    //pop b then a and push 1 when the comparison holds, 0 otherwise
+    fn compare(&mut self, int: fn(i64, i64) -> bool, float: fn(f64, f64) -> bool) -> Result<()> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        self.stack.push(Value::Int(a.compare(b, int, float) as i64));
+        Ok(())
+    }
+
+    //value to put in a cell holding `old`, a cell keeps the kind of value it started with
+    fn store(&self, old: Value, value: Value) -> Result<Value> {
+        match (old, value) {
+            (Value::Float(_), Value::Int(value)) => Ok(Value::Float(value as f64)),
+            (Value::Int(_), Value::Float(value)) => Err(self.error(format!(
+                "cannot store the real number {:?} in an int variable",
+                value
+            ))),
+            _ => Ok(value),
+        }
+    }
+
+    //index into memory for an address
+    fn cell(&self, address: usize) -> Result<usize> {
+        match address.checked_sub(self.base_address) {
+            Some(cell) if cell < self.memory.len() => Ok(cell),
+            _ => Err(self.error(format!("address {} is outside of memory", address))),
+        }
+    }
+
+    //jumps may land on any instruction or just past the last one
+    fn target(&self, target: usize) -> Result<usize> {
+        if target >= 1 && target <= self.code.len() + 1 {
+            Ok(target)
+        } else {
+            Err(self.error(format!(
+                "jump to instruction {}, the program has {}",
+                target,
+                self.code.len()
+            )))
+        }
+    }
+
+    //next number from the input
+    fn read(&mut self, input: &mut dyn BufRead) -> Result<Value> {
+        let word = match self.words.next(input)? {
+            Some(word) => word,
+            None => {
+                return Err(self.error("input ended before STDIN could read a value".to_string()))
+            }
+        };
+        Value::parse(&word)
+            .ok_or_else(|| self.error(format!("STDIN expected a number, found `{}`", word)))
+    }
+
+    fn error(&self, message: String) -> CompileError {
+        CompileError::Runtime {
+            instruction: self.pc,
+            message,
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//the stack machine, the interpreter and the C translation must print the same
+//  output for the same program and input, and stop on the same runtime errors
+use cpsc_323_compiler_project::{c_backend, interpreter, vm};
+use cpsc_323_compiler_project::{compile, CompileOptions, CompileResult};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+//what a run printed, and the runtime error it stopped on
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    output: String,
+    error: Option<String>,
+}
+
+fn compiled(source: &str) -> CompileResult {
+    let result = compile(source, &CompileOptions::default());
+    assert!(result.is_ok(), "{:?}", result.diagnostics);
+    result
+}
+
+fn machine(result: &CompileResult, input: &str) -> Outcome {
+    let mut output = Vec::new();
+    let ran = vm::run(
+        &result.instructions,
+        &result.symbols,
+        &mut input.as_bytes(),
+        &mut output,
+    );
+    Outcome {
+        output: String::from_utf8(output).unwrap(),
+        error: ran.err().map(|e| e.message()),
+    }
+}
+
+fn interpreted(result: &CompileResult, input: &str) -> Outcome {
+    let mut output = Vec::new();
+    let program = result.program.as_ref().unwrap();
+    let ran = interpreter::run(program, &result.symbols, &mut input.as_bytes(), &mut output);
+    Outcome {
+        output: String::from_utf8(output).unwrap(),
+        error: ran.err().map(|e| e.message()),
+    }
+}
+
+//None when there is no C compiler to build with
+fn translated(result: &CompileResult, name: &str, input: &str) -> Option<Outcome> {
+    if Command::new("cc").arg("--version").output().is_err() {
+        return None;
+    }
+    let program = result.program.as_ref().unwrap();
+    let source = c_backend::generate(program, &result.symbols).unwrap();
+
+    let dir = env::temp_dir().join(format!("rat-backends-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let c_file = dir.join(format!("{}.c", name));
+    let binary = dir.join(name);
+    fs::write(&c_file, source).unwrap();
+    let built = Command::new("cc")
+        .args(["-std=c99", "-pedantic", "-Wall", "-o"])
+        .arg(&binary)
+        .arg(&c_file)
+        .output()
+        .unwrap();
+    assert!(
+        built.status.success(),
+        "{}",
+        String::from_utf8_lossy(&built.stderr)
+    );
+
+    let mut child = Command::new(&binary)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let ran = child.wait_with_output().unwrap();
+
+    //the C program reports "runtime error: <message>" and exits with 1
+    let stderr = String::from_utf8(ran.stderr).unwrap();
+    let error = if ran.status.success() {
+        None
+    } else {
+        Some(
+            stderr
+                .trim()
+                .trim_start_matches("runtime error: ")
+                .to_string(),
+        )
+    };
+    fs::remove_file(&c_file).unwrap();
+    fs::remove_file(&binary).unwrap();
+    Some(Outcome {
+        output: String::from_utf8(ran.stdout).unwrap(),
+        error,
+    })
+}
+
+//run a program on every backend, they must agree on its output and error
+//  the machine's error is compared without its instruction number
+fn agree(name: &str, source: &str, input: &str) -> Outcome {
+    let result = compiled(source);
+    let machine = machine(&result, input);
+    let interpreted = interpreted(&result, input);
+
+    let without_instruction = |outcome: &Outcome| Outcome {
+        output: outcome.output.clone(),
+        error: outcome
+            .error
+            .as_ref()
+            .map(|e| e.split(" at instruction ").next().unwrap().to_string()),
+    };
+    assert_eq!(
+        without_instruction(&machine),
+        interpreted,
+        "{}: machine and interpreter",
+        name
+    );
+    if let Some(translated) = translated(&result, name, input) {
+        assert_eq!(interpreted, translated, "{}: interpreter and C", name);
+    }
+    interpreted
+}
+
+#[test]
+fn control_flow() {
+    let source = "begin
+        int i, total;
+        bool done;
+        total = 0;
+        for i = 1, 5 do total = total + i forend;
+        output(total);
+        i = 0;
+        while i < 3 do begin int j; j = i * 2; output(j); i = i + 1 end whileend;
+        do i = i - 1 doend while i > 0;
+        output(i);
+        done = not (i > 0) and (total == 15 or false);
+        if i < 0 then output(1) else if done then output(2) else output(3) endif;
+        output(done)
+    end";
+    let outcome = agree("control_flow", source, "");
+    assert_eq!(outcome.output, "15\n0\n2\n4\n0\n2\n1\n");
+}
+
+#[test]
+fn numbers() {
+    let source = "begin
+        int n, m;
+        float x, y;
+        input(n, x);
+        output(n, x);
+        x = 0.00001; output(x);
+        x = 0.0001; output(x);
+        y = 1.0 / 3.0; output(y);
+        y = 2; output(y);
+        output(100000000000000000000.0, 0.1 + 0.2);
+        m = - -5; output(m, -m);
+        m = -9223372036854775808; output(m);
+        output(n / 2, n * 2.5, 7 / -2)
+    end";
+    let outcome = agree("numbers", source, "7 2.5");
+    assert_eq!(
+        outcome.output,
+        "7\n2.5\n1e-5\n0.0001\n0.3333333333333333\n2.0\n1e20\n0.30000000000000004\n\
+         5\n-5\n-9223372036854775808\n3\n17.5\n-3\n"
+    );
+}
+
+//floats overflow to infinity instead of stopping the program
+#[test]
+fn float_overflow() {
+    let source = "begin
+        float x; int i;
+        x = 10.0;
+        for i = 1, 400 do x = x * 10.0 forend;
+        output(x, -x, x - x)
+    end";
+    let outcome = agree("float_overflow", source, "");
+    assert_eq!(outcome.output, "inf\n-inf\nNaN\n");
+    assert_eq!(outcome.error, None);
+}
+
+#[test]
+fn division_by_zero() {
+    let source = "begin int a; a = 0; output(1); output(5 / a) end";
+    let outcome = agree("division_by_zero", source, "");
+    assert_eq!(outcome.output, "1\n");
+    assert_eq!(outcome.error.as_deref(), Some("division by zero"));
+}
+
+#[test]
+fn integer_overflow() {
+    let source = "begin int a; a = 9223372036854775807; output(a); a = a + 1 end";
+    let outcome = agree("integer_overflow", source, "");
+    assert_eq!(outcome.output, "9223372036854775807\n");
+    assert_eq!(outcome.error.as_deref(), Some("integer overflow"));
+}
+
+//the machine's memory starts at zero, only the interpreter tracks unset variables
+#[test]
+fn uninitialized_read() {
+    let result = compiled("begin int a, b; b = 1; output(b); output(a + b) end");
+    let outcome = interpreted(&result, "");
+    assert_eq!(outcome.output, "1\n");
+    assert_eq!(
+        outcome.error.as_deref(),
+        Some("`a` is read before it is given a value")
+    );
+}
@@ -0,0 +1,41 @@
+//rendered errors, as the binary prints them without color
+use cpsc_323_compiler_project::diagnostics::{render_all, Renderer};
+use cpsc_323_compiler_project::{compile, CompileOptions};
+
+fn rendered(source: &str) -> String {
+    let options = CompileOptions {
+        file_name: Some("sample.txt".to_string()),
+        ..CompileOptions::default()
+    };
+    let result = compile(source, &options);
+    render_all(&Renderer::plain(), &result.diagnostics, source)
+}
+
+//the expected tokens come from what is on the parser stack, the statement can go
+//  on with an operator or end, but nothing that only follows an expression
+//  elsewhere, like `)`, `then` or `do`
+#[test]
+fn expected_after_an_expression() {
+    assert_eq!(
+        rendered("begin int a; a = 4 + 6 x end"),
+        "error: expected `+`, `-`, `*`, `/`, `;`, `end`, `<`, `<=`, `==`, `<>`, `>=`, `>`, \
+         `and` or `or`, found `x`\n \
+         --> sample.txt:1:24\n  \
+         |\n\
+         1 | begin int a; a = 4 + 6 x end\n  \
+         |                        ^\n\n"
+    );
+}
+
+//after a whole statement only a separator or the end of the block can follow
+#[test]
+fn expected_after_a_statement() {
+    assert_eq!(
+        rendered("begin int a; input(a) x end"),
+        "error: expected `;` or `end`, found `x`\n \
+         --> sample.txt:1:23\n  \
+         |\n\
+         1 | begin int a; input(a) x end\n  \
+         |                       ^\n\n"
+    );
+}
@@ -0,0 +1,129 @@
+//programs nested right up to ast::MAX_NESTING compile and run on the default
+//  test thread stack, one level more is reported instead of overflowing it
+use cpsc_323_compiler_project::ast::MAX_NESTING;
+use cpsc_323_compiler_project::{compile, interpreter, vm, CompileOptions};
+
+//each shape nested `n` levels inside the program's own begin ... end, along with
+//  the text the diagnostic points at once `n` is too deep, the last one in the
+//  source
+fn shapes(n: usize) -> Vec<(&'static str, String, &'static str)> {
+    vec![
+        (
+            "parentheses",
+            format!("begin int a; a = {}1{} end", "(".repeat(n), ")".repeat(n)),
+            "(",
+        ),
+        (
+            "sum",
+            format!("begin int a; a = 0{}; output(a) end", " + 1".repeat(n)),
+            "+",
+        ),
+        (
+            "or",
+            format!(
+                "begin bool b; b = false{}; output(b) end",
+                " or true".repeat(n)
+            ),
+            "or",
+        ),
+        (
+            "minus",
+            format!("begin int a; a = 1; a = {}a; output(a) end", "- ".repeat(n)),
+            "-",
+        ),
+        (
+            "not",
+            format!("begin bool b; b = {}true; output(b) end", "not ".repeat(n)),
+            "not",
+        ),
+        (
+            "blocks",
+            format!(
+                "begin int a; {}a = 1{}; output(a) end",
+                "begin ".repeat(n),
+                " end".repeat(n)
+            ),
+            "a = 1",
+        ),
+        (
+            "loops",
+            format!(
+                "begin int a; a = 1; {}a = 0{}; output(a) end",
+                "while a > 0 do ".repeat(n),
+                " whileend".repeat(n)
+            ),
+            ">",
+        ),
+        (
+            "else if",
+            format!(
+                "begin int a; a = 1; if a > 1 then a = 2{} endif; output(a) end",
+                " else if a > 1 then a = 3".repeat(n - 1)
+            ),
+            ">",
+        ),
+        (
+            "mixed",
+            format!(
+                "begin int a; {}a = {}1{}{} end",
+                "begin ".repeat(n / 2),
+                "(".repeat(n - n / 2),
+                ")".repeat(n - n / 2),
+                " end".repeat(n / 2)
+            ),
+            "(",
+        ),
+    ]
+}
+
+#[test]
+fn nesting_up_to_the_limit_runs() {
+    for (name, source, _) in shapes(MAX_NESTING - 1) {
+        let result = compile(&source, &CompileOptions::default());
+        assert!(result.is_ok(), "{}: {:?}", name, result.diagnostics);
+
+        let program = result.program.as_ref().unwrap();
+        let mut interpreted = Vec::new();
+        interpreter::run(program, &result.symbols, &mut &b""[..], &mut interpreted).unwrap();
+        let mut executed = Vec::new();
+        vm::run(
+            &result.instructions,
+            &result.symbols,
+            &mut &b""[..],
+            &mut executed,
+        )
+        .unwrap();
+        assert_eq!(interpreted, executed, "{}", name);
+    }
+}
+
+#[test]
+fn nesting_past_the_limit_is_reported() {
+    for (name, source, at) in shapes(MAX_NESTING) {
+        let result = compile(&source, &CompileOptions::default());
+        assert_eq!(
+            result.diagnostics.len(),
+            1,
+            "{}: {:?}",
+            name,
+            result.diagnostics
+        );
+
+        let error = &result.diagnostics[0];
+        assert_eq!(error.message(), "program is nested too deeply", "{}", name);
+        let span = error.location().unwrap().span;
+        assert_eq!(span.start.offset, source.rfind(at).unwrap(), "{}", name);
+    }
+}
+
+//far past the limit, a chain is reported before any of it is lowered
+#[test]
+fn long_chain_is_reported() {
+    let source = format!("begin int a; a = 0{} end", " + 1".repeat(20_000));
+    let result = compile(&source, &CompileOptions::default());
+    assert_eq!(result.diagnostics.len(), 1);
+    assert_eq!(
+        result.diagnostics[0].message(),
+        "program is nested too deeply"
+    );
+}
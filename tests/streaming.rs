@@ -0,0 +1,115 @@
+//the streaming pipeline must give what the batch pipeline gives on the whole text
+use cpsc_323_compiler_project::{
+    compile, compile_file, compile_reader, CompileError, CompileOptions, CompileResult, Stage,
+};
+use std::io::{self, BufRead, Read};
+
+//hands the source out a few bytes at a time, so tokens and characters are split
+//  across reads
+struct Trickle<'a> {
+    bytes: &'a [u8],
+    size: usize,
+}
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = self.fill_buf()?.len().min(buf.len());
+        buf[..length].copy_from_slice(&self.bytes[..length]);
+        self.consume(length);
+        Ok(length)
+    }
+}
+
+impl<'a> BufRead for Trickle<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.bytes[..self.size.min(self.bytes.len())])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.bytes = &self.bytes[amount..];
+    }
+}
+
+const SOURCES: &[&str] = &[
+    include_str!("../src/sample_input.txt"),
+    include_str!("../src/sample_input3.txt"),
+    "",
+    "begin int a; float x; x = .5 + 2. * -a; output(x) end",
+    "begin int ünïcode; ünïcode = 1; output(ünïcode) end ! cömment !",
+    "begin int a; a = 9223372036854775807 + -9223372036854775808 end",
+    "begin int a; a = 1 +; a = ; b = 2 end",
+    "begin int a; a = 1 +; a = 2 # end",
+    "begin int a ! never closed",
+    "begin int a; a = 1; output(a); end extra tokens",
+    "begin bool b; b = 1 + true end",
+];
+
+//everything a result holds, the symbol table's hash maps left out since their
+//  order changes from run to run
+fn summary(result: &CompileResult) -> String {
+    format!(
+        "{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{}",
+        result.tokens,
+        result.rules,
+        result.symbols.symbols(),
+        result.instructions,
+        result.program,
+        result.diagnostics,
+        result.truncated
+    )
+}
+
+fn batch(source: &str, options: &CompileOptions) -> CompileResult {
+    let mut result = compile(source, options);
+    //only Stage::Lex keeps the tokens when streaming
+    if options.stage != Stage::Lex {
+        result.tokens.clear();
+    }
+    result
+}
+
+#[test]
+fn streaming_matches_batch() {
+    let stages = [Stage::Lex, Stage::Parse, Stage::Symbols, Stage::Code];
+    for source in SOURCES.iter() {
+        for stage in stages.iter() {
+            for size in [1, 3, 4096].iter() {
+                let options = CompileOptions {
+                    stage: *stage,
+                    error_limit: 2,
+                    ..CompileOptions::default()
+                };
+                let mut input = Trickle {
+                    bytes: source.as_bytes(),
+                    size: *size,
+                };
+                let streamed = compile_reader(&mut input, &options);
+                assert_eq!(
+                    summary(&streamed),
+                    summary(&batch(source, &options)),
+                    "{:?} at {:?} read {} bytes at a time",
+                    source,
+                    stage,
+                    size
+                );
+            }
+        }
+    }
+}
+
+//a read error names the file it came from, like every other diagnostic
+#[test]
+fn read_error_names_the_file() {
+    let path = std::env::temp_dir().join(format!("rat-invalid-{}.txt", std::process::id()));
+    std::fs::write(&path, b"begin int a; \xff end").unwrap();
+    let path = path.to_str().unwrap().to_string();
+    let result = compile_file(&path, &CompileOptions::default());
+    std::fs::remove_file(&path).unwrap();
+
+    let result = result.unwrap();
+    assert_eq!(result.diagnostics.len(), 1);
+    match &result.diagnostics[0] {
+        CompileError::Io { path: named, .. } => assert_eq!(named, &path),
+        other => panic!("expected a read error, found {:?}", other),
+    }
+}